@@ -0,0 +1,17 @@
+#version 330 core
+
+out vec4 FragColor;
+
+in vec2 TexCoord;
+in vec4 v_color;
+in float v_texture_index;
+
+uniform sampler2D u_textures[32];
+
+void main()
+{
+    int index = int(v_texture_index);
+    vec4 color = texture(u_textures[index], TexCoord) * v_color;
+    float gray = dot(color.rgb, vec3(0.299, 0.587, 0.114));
+    FragColor = vec4(vec3(gray), color.a);
+}
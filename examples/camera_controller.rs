@@ -0,0 +1,78 @@
+use smol_rs::camera::{CameraController, Smoothing};
+use smol_rs::errors::SmolError;
+use smol_rs::math::{Vector2, Vector3};
+use smol_rs::{import_file, App, AppSettings, Color, Keycode, Rectangle, Transform};
+
+extern crate smol_rs;
+
+fn main() -> Result<(), SmolError> {
+    let mut app = App::new(AppSettings::default());
+
+    let t = app.load_texture(import_file!("../assets/test.png"))?;
+
+    let mut player = Vector2::<f32>::default();
+    let mut controller = CameraController::new(&app.renderer.camera);
+    controller.smoothing = Smoothing::Spring {
+        stiffness: 40.,
+        damping: 12.,
+    };
+    controller.deadzone = Some(Rectangle {
+        x: -40.,
+        y: -30.,
+        width: 80.,
+        height: 60.,
+    });
+    controller.bounds = Some(Rectangle {
+        x: -1000.,
+        y: -1000.,
+        width: 2000.,
+        height: 2000.,
+    });
+
+    while app.is_running() {
+        let mut direction = Vector2::<f32>::default();
+        for key in app.input.get_pressed_keys().iter() {
+            match key {
+                Keycode::Up => direction.y -= 1.,
+                Keycode::Down => direction.y += 1.,
+                Keycode::Right => direction.x += 1.,
+                Keycode::Left => direction.x -= 1.,
+                _ => {}
+            }
+        }
+
+        if direction.magnitude() > 0.1 {
+            player += direction.normalize() * 300. * app.delta;
+        }
+
+        if app.input.is_key_down(Keycode::Space) {
+            controller.add_trauma(0.5);
+        }
+
+        if app.input.mouse_scroll_direction > 0 {
+            controller.target_zoom += 0.5;
+        } else if app.input.mouse_scroll_direction < 0 {
+            controller.target_zoom -= 0.5;
+        }
+
+        controller.follow(player);
+        let render_size = app.renderer.render_size();
+        controller.update(&mut app.renderer.camera, render_size, app.delta);
+
+        app.renderer.clear(Color::BLACK);
+
+        app.renderer.texture(Transform::default(), &t);
+        app.renderer.rectangle_transform(
+            Transform {
+                position: Vector3::from([player.x, player.y, 0.]),
+                scale: Vector3::from([16., 16., 1.]),
+                ..Default::default()
+            },
+            Color::RED,
+        );
+
+        app.end_scene();
+    }
+
+    Ok(())
+}
@@ -1,5 +1,6 @@
 use smol_rs::errors::SmolError;
-use smol_rs::math::Vector3;
+use smol_rs::math::{Vector, Vector2, Vector3};
+use smol_rs::renderer::text::{TextAlignment, TextOutline, TextSettings, TextShadow, TextSpace};
 
 use smol_rs::{import_file, App, AppSettings, Color, Keycode, Transform};
 
@@ -15,6 +16,17 @@ fn main() -> Result<(), SmolError> {
         .load_texture(import_file!("../assets/test.png"))
         .unwrap();
 
+    let font = app
+        .load_font(import_file!("../assets/OpenSans-SemiBold.ttf"))
+        .unwrap();
+
+    // stays sharp while zooming in
+    let sdf_font = app
+        .load_sdf_font(import_file!("../assets/OpenSans-Light.ttf"))
+        .unwrap();
+
+    let mut time = 0.;
+
     while app.is_running() {
         let mut position = Vector3::default();
         let mut zoom = app.renderer.camera.zoom;
@@ -24,6 +36,8 @@ fn main() -> Result<(), SmolError> {
                 Keycode::Down => position.y += 1.,
                 Keycode::Right => position.x += 1.,
                 Keycode::Left => position.x -= 1.,
+                Keycode::Q => app.renderer.camera.rotation -= app.delta,
+                Keycode::E => app.renderer.camera.rotation += app.delta,
                 _ => {}
             }
         }
@@ -44,6 +58,54 @@ fn main() -> Result<(), SmolError> {
 
         app.renderer.texture(Transform::default(), &t);
 
+        app.renderer.text_ex(
+            &sdf_font,
+            "World text",
+            &TextSettings {
+                color: Color::WHITE,
+                alignment: TextAlignment::Center,
+                position: Vector::from([0., -t.uv_size.y / 2. - 50.]),
+                size: Some(24.),
+                space: TextSpace::World,
+                outline: Some(TextOutline {
+                    width: 2.,
+                    color: Color::BLUE,
+                }),
+                shadow: Some(TextShadow {
+                    offset: Vector::from([2., 3.]),
+                    softness: 2.,
+                    color: Color(0, 0, 0, 0.6),
+                }),
+                ..Default::default()
+            },
+        );
+
+        // a damage number popping up and wobbling
+        time += app.delta;
+        let pop = 1. + (time * 4.).sin().abs() * 0.5;
+        app.renderer.text_ex(
+            &font,
+            "-12",
+            &TextSettings {
+                color: Color::RED,
+                position: Vector::from([t.uv_size.x / 2., -t.uv_size.y / 2.]),
+                size: Some(24.),
+                space: TextSpace::World,
+                transform: Some(Transform {
+                    rotation: Vector::from([0., 0., (time * 3.).sin() * 0.3]),
+                    scale: Vector::from([pop, pop, 1.]),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+        );
+
+        let mouse_pos = app.input.get_mouse_pos();
+        let mouse_world = app
+            .renderer
+            .screen_to_world(Vector2::from([mouse_pos.x as f32, mouse_pos.y as f32]));
+        app.renderer.circle(mouse_world, 4., 16, Color::RED);
+
         app.end_scene();
     }
 
@@ -1,7 +1,11 @@
 use nalgebra::Vector;
 use smol_rs::errors::SmolError;
-use smol_rs::renderer::text::{TextAlignment, TextSettings};
-use smol_rs::{import_file, App, AppSettings, Color};
+use smol_rs::renderer::rich_text::parse_markup;
+use smol_rs::renderer::text::{
+    TextAlignment, TextOutline, TextOverflow, TextSettings, TextVerticalAlignment,
+};
+use smol_rs::renderer::text_effects::GlyphTransform;
+use smol_rs::{import_file, App, AppSettings, Color, Rectangle};
 
 extern crate smol_rs;
 
@@ -16,7 +20,16 @@ fn main() -> Result<(), SmolError> {
         .load_font(import_file!("../assets/OpenSans-Light.ttf"))
         .unwrap();
 
+    let tooltip = parse_markup(
+        "You found the [color=#ffcc00][font=OpenSans-SemiBold]Sword of Dawn[/font][/color], \
+         it deals [size=48][color=red]crit[/color][/size] damage",
+        |name| app.get_font(name).copied(),
+    )?;
+
+    let mut time = 0.;
+
     while app.is_running() {
+        time += app.delta;
         app.renderer.clear(Color::BLACK);
 
         app.renderer.text_ex(
@@ -29,6 +42,20 @@ fn main() -> Result<(), SmolError> {
             },
         );
 
+        let label_settings = TextSettings {
+            color: Color::WHITE,
+            alignment: TextAlignment::Center,
+            position: Vector::from([0., -200.]),
+            size: Some(32.),
+            ..Default::default()
+        };
+        let label_bounds = app
+            .renderer
+            .measure_text(&open_sans_bold, "Start", &label_settings);
+        app.renderer.line_rect(label_bounds, 2., Color::GREEN);
+        app.renderer
+            .text_ex(&open_sans_bold, "Start", &label_settings);
+
         app.renderer.text_ex(
             &open_sans_light,
             "Nullam ac luctus erat. Curabitur dignissim consequat sapien sed cursus.",
@@ -47,6 +74,62 @@ fn main() -> Result<(), SmolError> {
                 color: Color::WHITE,
                 alignment: TextAlignment::Center,
                 position: Vector::from([50., 50.]),
+                size: Some(24.),
+                letter_spacing: 2.,
+                line_height: 1.2,
+                max_width: Some(300.),
+                ..Default::default()
+            },
+        );
+
+        let dialog = Rectangle {
+            x: 250.,
+            y: 150.,
+            width: 320.,
+            height: 90.,
+        };
+        app.renderer.line_rect(dialog, 2., Color::WHITE);
+        app.renderer.text_ex(
+            &open_sans_light,
+            "Pellentesque habitant morbi tristique senectus et netus et malesuada fames ac turpis \
+             egestas. Vestibulum tortor quam, feugiat vitae ultricies eget.",
+            &TextSettings {
+                color: Color::WHITE,
+                bounds: Some(dialog),
+                vertical_alignment: TextVerticalAlignment::Center,
+                overflow: TextOverflow::Ellipsis,
+                size: Some(24.),
+                ..Default::default()
+            },
+        );
+
+        // typed out a character at a time, then waving
+        app.renderer.text_ex(
+            &open_sans_bold,
+            "The ghost wails at you!",
+            &TextSettings {
+                color: Color::WHITE,
+                position: Vector::from([250., 280.]),
+                size: Some(28.),
+                outline: Some(TextOutline {
+                    width: 1.5,
+                    color: Color::BLUE,
+                }),
+                visible_chars: Some((time * 15.) as usize),
+                glyph_transform: Some(Box::new(move |glyph| {
+                    GlyphTransform::wave(glyph, time, 4., 6.)
+                })),
+                ..Default::default()
+            },
+        );
+
+        app.renderer.rich_text(
+            &open_sans_light,
+            &tooltip,
+            &TextSettings {
+                color: Color::WHITE,
+                position: Vector::from([-600., 200.]),
+                size: Some(32.),
                 ..Default::default()
             },
         );
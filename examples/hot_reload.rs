@@ -0,0 +1,36 @@
+use nalgebra::Vector;
+use smol_rs::errors::SmolError;
+
+use smol_rs::{App, AppSettings, Color, Transform};
+
+extern crate smol_rs;
+
+// run from the repository root and edit `assets/test.png` or the shaders while it's open
+fn main() -> Result<(), SmolError> {
+    let mut app = App::new(AppSettings {
+        hot_reload: true,
+        ..Default::default()
+    });
+
+    let texture = app.load_texture_from_path("test.png")?;
+    let grayscale = app.load_shader_from_path("shaders/sprite.vs", "shaders/grayscale.fs")?;
+
+    while app.is_running() {
+        app.renderer.clear(Color::BLACK);
+
+        app.renderer.texture(
+            Transform::from(Vector::from([-texture.uv_size.x / 2. - 10., 0.])),
+            &app.get_texture("test").unwrap(),
+        );
+
+        app.renderer.set_shader(grayscale);
+        app.renderer.texture(
+            Transform::from(Vector::from([texture.uv_size.x / 2. + 10., 0.])),
+            &app.get_texture("test").unwrap(),
+        );
+
+        app.end_scene();
+    }
+
+    Ok(())
+}
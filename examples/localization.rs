@@ -0,0 +1,65 @@
+use nalgebra::Vector;
+use smol_rs::errors::SmolError;
+use smol_rs::renderer::text::{TextAlignment, TextSettings};
+use smol_rs::{import_file, App, AppSettings, Color, Keycode};
+
+extern crate smol_rs;
+
+fn main() -> Result<(), SmolError> {
+    let mut app = App::new(AppSettings::default());
+
+    let font = app
+        .load_font(import_file!("../assets/OpenSans-SemiBold.ttf"))
+        .unwrap();
+
+    app.load_locale(import_file!("../assets/locales/en.ron"))?;
+    app.load_locale(import_file!("../assets/locales/fr.ron"))?;
+    app.load_locale(import_file!("../assets/locales/ru.ron"))?;
+    app.renderer.localization.set_fallback_locale(Some("en"));
+
+    let locales = ["en", "fr", "ru"];
+    let mut locale = 0;
+    let mut potions = 1;
+
+    while app.is_running() {
+        // L switches the language, up and down change the count
+        if app.input.is_key_pressed(Keycode::L) {
+            locale = (locale + 1) % locales.len();
+            app.renderer.localization.set_locale(locales[locale])?;
+        }
+        if app.input.is_key_pressed(Keycode::Up) {
+            potions += 1;
+        }
+        if app.input.is_key_pressed(Keycode::Down) && potions > 0 {
+            potions -= 1;
+        }
+
+        app.renderer.clear(Color::BLACK);
+
+        let settings = |y: f32| TextSettings {
+            color: Color::WHITE,
+            alignment: TextAlignment::Center,
+            position: Vector::from([0., y]),
+            size: Some(32.),
+            ..Default::default()
+        };
+        app.renderer
+            .text_key(&font, "menu.start", &[], &settings(0.));
+        app.renderer.text_key(
+            &font,
+            "shop.greeting",
+            &[("name", "Aria".into())],
+            &settings(50.),
+        );
+        app.renderer.text_key(
+            &font,
+            "inventory.count",
+            &[("count", potions.into())],
+            &settings(100.),
+        );
+
+        app.end_scene();
+    }
+
+    Ok(())
+}
@@ -0,0 +1,33 @@
+use nalgebra::Vector;
+use smol_rs::errors::SmolError;
+
+use smol_rs::{asset, App, AppSettings, Color, Transform};
+
+extern crate smol_rs;
+
+fn main() -> Result<(), SmolError> {
+    let mut app = App::new(AppSettings::default());
+
+    // regions of an image packed ahead of time, TexturePacker's JSON exports work the same way
+    let image = asset!(app, "test.png")?;
+    let descriptor = asset!(app, "test_atlas.ron")?;
+    let textures = app.load_packed_atlas(image.as_asset(), descriptor.as_asset())?;
+
+    while app.is_running() {
+        app.renderer.clear(Color::BLACK);
+
+        // drawn swapped around with a gap between them
+        app.renderer.texture(
+            Transform::from(Vector::from([0., 0.])),
+            &textures["test_right"],
+        );
+        app.renderer.texture(
+            Transform::from(Vector::from([100., 0.])),
+            &textures["test_left"],
+        );
+
+        app.end_scene();
+    }
+
+    Ok(())
+}
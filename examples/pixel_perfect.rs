@@ -0,0 +1,32 @@
+use nalgebra::Vector;
+use smol_rs::errors::SmolError;
+use smol_rs::renderer::ScalingMode;
+use smol_rs::{import_file, App, AppSettings, Color, Transform};
+
+extern crate smol_rs;
+
+fn main() -> Result<(), SmolError> {
+    let mut app = App::new(AppSettings {
+        virtual_resolution: Some(Vector::from([320, 180])),
+        scaling: ScalingMode::IntegerScale,
+        ..Default::default()
+    });
+
+    let t = app.load_texture(import_file!("../assets/test.png"))?;
+
+    while app.is_running() {
+        app.renderer.clear(Color::BLACK);
+
+        app.renderer.texture(Transform::default(), &t);
+
+        let mouse_pos = app.input.get_mouse_pos();
+        let mouse_world = app
+            .renderer
+            .screen_to_world(Vector::from([mouse_pos.x as f32, mouse_pos.y as f32]));
+        app.renderer.circle(mouse_world, 2., 8, Color::RED);
+
+        app.end_scene();
+    }
+
+    Ok(())
+}
@@ -0,0 +1,82 @@
+use smol_rs::camera::{Camera, RenderLayers, Viewport};
+use smol_rs::errors::SmolError;
+use smol_rs::{import_file, App, AppSettings, Color, Keycode, Rectangle, Transform};
+
+extern crate smol_rs;
+
+const MINIMAP_LAYER: u8 = 1;
+
+fn main() -> Result<(), SmolError> {
+    let mut app = App::new(AppSettings::default());
+
+    let t = app.load_texture(import_file!("../assets/test.png"))?;
+
+    let mut left = Camera {
+        viewport: Viewport::Normalized(Rectangle {
+            x: 0.,
+            y: 0.,
+            width: 0.5,
+            height: 1.,
+        }),
+        layers: RenderLayers::ALL.without(MINIMAP_LAYER),
+        ..Default::default()
+    };
+
+    let mut right = Camera {
+        viewport: Viewport::Normalized(Rectangle {
+            x: 0.5,
+            y: 0.,
+            width: 0.5,
+            height: 1.,
+        }),
+        ..left
+    };
+
+    let minimap = Camera {
+        zoom: 0.25,
+        viewport: Viewport::Pixels(Rectangle {
+            x: 1280. - 210.,
+            y: 10.,
+            width: 200.,
+            height: 120.,
+        }),
+        layers: RenderLayers::ALL,
+        ..Default::default()
+    };
+
+    while app.is_running() {
+        for key in app.input.get_pressed_keys().iter() {
+            match key {
+                Keycode::A => left.position.x -= 200. * app.delta,
+                Keycode::D => left.position.x += 200. * app.delta,
+                Keycode::Left => right.position.x -= 200. * app.delta,
+                Keycode::Right => right.position.x += 200. * app.delta,
+                _ => {}
+            }
+        }
+
+        for camera in [left, right, minimap].iter() {
+            app.renderer.set_camera(*camera);
+            app.renderer.clear(Color::BLACK);
+
+            app.renderer.set_layer(0);
+            app.renderer.texture(Transform::default(), &t);
+
+            app.renderer.set_layer(MINIMAP_LAYER);
+            app.renderer.line_rect(
+                Rectangle {
+                    x: -320.,
+                    y: -180.,
+                    width: 640.,
+                    height: 360.,
+                },
+                4.,
+                Color::GREEN,
+            );
+        }
+
+        app.end_scene();
+    }
+
+    Ok(())
+}
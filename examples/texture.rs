@@ -1,15 +1,16 @@
 use nalgebra::Vector;
 use smol_rs::errors::SmolError;
 
-use smol_rs::{import_file, App, AppSettings, Color, Transform};
+use smol_rs::{asset, App, AppSettings, Color, Transform};
 
 extern crate smol_rs;
 
 fn main() -> Result<(), SmolError> {
     let mut app = App::new(AppSettings::default());
 
-    app.load_texture(import_file!("../assets/test.png"))
-        .unwrap();
+    // read from `assets/` while running, embedded with `--features embed-assets`
+    let test = asset!(app, "test.png")?;
+    app.load_texture(test.as_asset())?;
 
     while app.is_running() {
         app.renderer.clear(Color::BLACK);
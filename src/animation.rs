@@ -0,0 +1,384 @@
+mod aseprite;
+
+use crate::asset_store::AtlasSettings;
+use crate::errors::SmolError;
+use crate::math::Vector2;
+use crate::renderer::shapes::Rectangle;
+use crate::renderer::Texture;
+use crate::Color;
+
+pub use asefile::BlendMode;
+
+/// Order the frames of a tag play in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AnimationDirection {
+    #[default]
+    Forward,
+    Reverse,
+    /// Forward to the last frame, then back to the first
+    PingPong,
+}
+
+impl From<asefile::AnimationDirection> for AnimationDirection {
+    fn from(direction: asefile::AnimationDirection) -> Self {
+        match direction {
+            asefile::AnimationDirection::Forward => AnimationDirection::Forward,
+            asefile::AnimationDirection::Reverse => AnimationDirection::Reverse,
+            asefile::AnimationDirection::PingPong => AnimationDirection::PingPong,
+        }
+    }
+}
+
+/// Named range of frames, like `run` or `idle`
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnimationTag {
+    pub name: String,
+    /// First frame of the tag
+    pub from: usize,
+    /// Last frame of the tag, it's played too
+    pub to: usize,
+    pub direction: AnimationDirection,
+}
+
+/// Text and color set on a layer or slice in Aseprite's properties
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct UserData {
+    pub text: Option<String>,
+    pub color: Option<Color>,
+}
+
+#[derive(Debug, Clone)]
+pub struct SpriteLayer {
+    pub name: String,
+    /// Whether it and its parent groups are shown, hidden layers aren't part of the frames
+    pub visible: bool,
+    /// From 0 to 1
+    pub opacity: f32,
+    pub blend_mode: BlendMode,
+    pub user_data: Option<UserData>,
+    /// The layer on its own for every frame, only loaded with `AsepriteSettings::split_layers`
+    pub frames: Vec<Texture>,
+}
+
+/// Shape of a slice from one frame on, positions are in pixels from the sprite's top left
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct SliceKey {
+    /// First frame the key is used for, until the next key
+    pub from_frame: usize,
+    pub bounds: Rectangle,
+    /// Center of a 9-patch slice, relative to `bounds`
+    pub center: Option<Rectangle>,
+    /// Relative to `bounds`
+    pub pivot: Option<Vector2<f32>>,
+}
+
+/// Named rectangle authored in Aseprite, useful for hitboxes and attach points
+#[derive(Debug, Clone, Default)]
+pub struct SpriteSlice {
+    pub name: String,
+    pub keys: Vec<SliceKey>,
+    pub user_data: Option<UserData>,
+}
+
+impl SpriteSlice {
+    /// Shape of the slice on a frame, `None` before its first key
+    pub fn key(&self, frame: usize) -> Option<&SliceKey> {
+        self.keys.iter().rev().find(|key| key.from_frame <= frame)
+    }
+}
+
+/// How an aseprite file is loaded by `App::load_aseprite_animation_with_settings`
+#[derive(Debug, Clone, Default)]
+pub struct AsepriteSettings {
+    /// Also packs every layer of every frame as its own texture, see `SpriteLayer::frames`
+    pub split_layers: bool,
+    /// How the frames are packed
+    pub atlas: AtlasSettings,
+}
+
+/// Frames of an animation packed into an atlas, see `App::load_aseprite_animation`
+#[derive(Debug, Clone, Default)]
+pub struct SpriteSheet {
+    /// Every visible layer flattened, like Aseprite's export
+    pub frames: Vec<Texture>,
+    /// Seconds each frame is shown for
+    pub durations: Vec<f32>,
+    pub tags: Vec<AnimationTag>,
+    /// From the bottom layer up, groups aren't included
+    pub layers: Vec<SpriteLayer>,
+    pub slices: Vec<SpriteSlice>,
+}
+
+impl SpriteSheet {
+    pub fn tag(&self, name: &str) -> Option<&AnimationTag> {
+        self.tags.iter().find(|tag| tag.name == name)
+    }
+
+    pub fn layer(&self, name: &str) -> Option<&SpriteLayer> {
+        self.layers.iter().find(|layer| layer.name == name)
+    }
+
+    /// Shape of a slice on a frame
+    pub fn slice(&self, name: &str, frame: usize) -> Option<&SliceKey> {
+        self.slices
+            .iter()
+            .find(|slice| slice.name == name)
+            .and_then(|slice| slice.key(frame))
+    }
+
+    /// Seconds the whole sheet takes to play once
+    pub fn duration(&self) -> f32 {
+        self.durations.iter().sum()
+    }
+}
+
+/// Plays the frames of a sprite sheet, advanced with `App::delta` every frame:
+///
+/// ```ignore
+/// let sheet = app.load_aseprite_animation(import_file!("../assets/player.aseprite")).unwrap();
+/// let mut player = AnimationPlayer::new(sheet);
+/// player.play("run").unwrap();
+///
+/// while app.is_running() {
+///     player.update(app.delta);
+///     app.renderer.texture(Transform::default(), &player.texture());
+///     app.end_scene();
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct AnimationPlayer {
+    sheet: SpriteSheet,
+    tag: Option<String>,
+    from: usize,
+    to: usize,
+    direction: AnimationDirection,
+    frame: usize,
+    /// Seconds the current frame has been shown for
+    elapsed: f32,
+    /// Whether a ping-pong animation is on its way back
+    backwards: bool,
+    finished: bool,
+    /// Multiplies the frame durations' speed, 2 plays twice as fast
+    pub speed: f32,
+    /// Animations that don't loop stop on their last frame
+    pub looping: bool,
+}
+
+impl AnimationPlayer {
+    /// Plays every frame of the sheet forward, looping
+    pub fn new(sheet: SpriteSheet) -> Self {
+        let to = sheet.frames.len().saturating_sub(1);
+
+        AnimationPlayer {
+            sheet,
+            tag: None,
+            from: 0,
+            to,
+            direction: AnimationDirection::Forward,
+            frame: 0,
+            elapsed: 0.,
+            backwards: false,
+            finished: false,
+            speed: 1.,
+            looping: true,
+        }
+    }
+
+    /// Starts playing a tag of the sheet, calling it again with the tag that's already playing
+    /// doesn't restart it so it can be called every frame. Frames of the tag past the end of
+    /// the sheet are left out
+    pub fn play(&mut self, tag: &str) -> Result<(), SmolError> {
+        if self.tag.as_deref() == Some(tag) && !self.finished {
+            return Ok(());
+        }
+
+        let found = self
+            .sheet
+            .tag(tag)
+            .ok_or_else(|| SmolError::new(format!("Sprite sheet has no tag named {}", tag)))?;
+        let last = self.sheet.frames.len().saturating_sub(1);
+        self.to = found.to.min(last);
+        self.from = found.from.min(self.to);
+        self.direction = found.direction;
+        self.tag = Some(tag.to_owned());
+        self.restart();
+
+        Ok(())
+    }
+
+    /// Goes back to the first frame of what's playing
+    pub fn restart(&mut self) {
+        self.frame = match self.direction {
+            AnimationDirection::Reverse => self.to,
+            _ => self.from,
+        };
+        self.elapsed = 0.;
+        self.backwards = false;
+        self.finished = false;
+    }
+
+    pub fn update(&mut self, delta: f32) {
+        if self.finished || self.sheet.frames.is_empty() {
+            return;
+        }
+
+        self.elapsed += delta * self.speed;
+        loop {
+            // frames without a duration would never be left
+            let duration = self
+                .sheet
+                .durations
+                .get(self.frame)
+                .copied()
+                .unwrap_or_default()
+                .max(0.001);
+            if self.elapsed < duration {
+                break;
+            }
+            self.elapsed -= duration;
+            self.advance();
+
+            if self.finished {
+                self.elapsed = 0.;
+                break;
+            }
+        }
+    }
+
+    fn advance(&mut self) {
+        let forward = match self.direction {
+            AnimationDirection::Forward => true,
+            AnimationDirection::Reverse => false,
+            AnimationDirection::PingPong => !self.backwards,
+        };
+
+        if forward && self.frame < self.to {
+            self.frame += 1;
+        } else if !forward && self.frame > self.from {
+            self.frame -= 1;
+        } else if self.direction == AnimationDirection::PingPong {
+            // a ping-pong animation ends when it's back on its first frame
+            if forward || self.looping {
+                self.backwards = forward;
+                self.frame = if forward {
+                    self.to.saturating_sub(1).max(self.from)
+                } else {
+                    (self.from + 1).min(self.to)
+                };
+            } else {
+                self.finished = true;
+            }
+        } else if self.looping {
+            self.frame = if forward { self.from } else { self.to };
+        } else {
+            self.finished = true;
+        }
+    }
+
+    /// Index of the frame in the sheet that's showing
+    pub fn frame(&self) -> usize {
+        self.frame
+    }
+
+    /// Texture of the frame that's showing
+    pub fn texture(&self) -> Texture {
+        self.sheet
+            .frames
+            .get(self.frame)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Name of the tag that's playing, `None` when playing the whole sheet
+    pub fn tag(&self) -> Option<&str> {
+        self.tag.as_deref()
+    }
+
+    /// Whether an animation that doesn't loop got to its end
+    pub fn finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Shape of a slice on the frame that's showing
+    pub fn slice(&self, name: &str) -> Option<&SliceKey> {
+        self.sheet.slice(name, self.frame)
+    }
+
+    pub fn sheet(&self) -> &SpriteSheet {
+        &self.sheet
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn player(frames: usize, direction: AnimationDirection) -> AnimationPlayer {
+        let sheet = SpriteSheet {
+            frames: vec![Default::default(); frames],
+            durations: vec![1.; frames],
+            tags: vec![AnimationTag {
+                name: "tag".to_owned(),
+                from: 1,
+                to: 4,
+                direction,
+            }],
+            ..Default::default()
+        };
+
+        let mut player = AnimationPlayer::new(sheet);
+        player.play("tag").unwrap();
+        player
+    }
+
+    /// Frames shown over `count` seconds, starting with the current one
+    fn frames(player: &mut AnimationPlayer, count: usize) -> Vec<usize> {
+        let mut frames = vec![player.frame()];
+        for _ in 1..count {
+            player.update(1.);
+            frames.push(player.frame());
+        }
+        frames
+    }
+
+    #[test]
+    fn forward_loops_over_the_tag() {
+        let mut player = player(6, AnimationDirection::Forward);
+        assert_eq!(frames(&mut player, 6), [1, 2, 3, 4, 1, 2]);
+    }
+
+    #[test]
+    fn reverse_starts_on_the_last_frame() {
+        let mut player = player(6, AnimationDirection::Reverse);
+        assert_eq!(frames(&mut player, 6), [4, 3, 2, 1, 4, 3]);
+
+        player.looping = false;
+        player.restart();
+        assert_eq!(frames(&mut player, 6), [4, 3, 2, 1, 1, 1]);
+        assert!(player.finished());
+    }
+
+    #[test]
+    fn ping_pong_turns_around_at_the_ends() {
+        let mut player = player(6, AnimationDirection::PingPong);
+        assert_eq!(frames(&mut player, 9), [1, 2, 3, 4, 3, 2, 1, 2, 3]);
+
+        player.looping = false;
+        player.restart();
+        assert_eq!(frames(&mut player, 9), [1, 2, 3, 4, 3, 2, 1, 1, 1]);
+        assert!(player.finished());
+    }
+
+    #[test]
+    fn ping_pong_of_one_frame_stays_on_it() {
+        let mut player = player(2, AnimationDirection::PingPong);
+        assert_eq!(frames(&mut player, 3), [1, 1, 1]);
+    }
+
+    #[test]
+    fn tags_past_the_sheet_are_clamped() {
+        let mut player = player(3, AnimationDirection::Forward);
+        assert_eq!(frames(&mut player, 4), [1, 2, 1, 2]);
+        assert!(player.play("missing").is_err());
+    }
+}
@@ -0,0 +1,304 @@
+use asefile::AsepriteFile;
+use image::RgbaImage;
+use nalgebra::Vector;
+
+use super::{
+    AnimationTag, AsepriteSettings, SliceKey, SpriteLayer, SpriteSheet, SpriteSlice, UserData,
+};
+use crate::asset_store::{pack_images, Asset};
+use crate::errors::SmolError;
+use crate::renderer::shapes::Rectangle;
+use crate::{App, Color};
+
+const HEADER_SIZE: usize = 128;
+const FILE_MAGIC: u16 = 0xA5E0;
+const LAYER_CHUNK: u16 = 0x2004;
+const USER_DATA_CHUNK: u16 = 0x2020;
+const SLICE_CHUNK: u16 = 0x2022;
+
+impl App {
+    /// Packs every frame of an aseprite file into an atlas, the frames are stored as
+    /// `{name}_{index}` like `player_0`
+    pub fn load_aseprite_animation<'a>(
+        &mut self,
+        asset: Asset<'a>,
+    ) -> Result<SpriteSheet, SmolError> {
+        self.load_aseprite_animation_with_settings(asset, &AsepriteSettings::default())
+    }
+
+    /// Like `load_aseprite_animation`, split layers are stored as `{name}_{layer}_{index}`
+    pub fn load_aseprite_animation_with_settings<'a>(
+        &mut self,
+        asset: Asset<'a>,
+        settings: &AsepriteSettings,
+    ) -> Result<SpriteSheet, SmolError> {
+        let file = AsepriteFile::read(asset.2).map_err(|e| SmolError::new(e.to_string()))?;
+        let extras = read_extras(asset.2)?;
+
+        let frame_names = |prefix: &str| -> Vec<String> {
+            (0..file.num_frames())
+                .map(|index| format!("{}_{}", prefix, index))
+                .collect()
+        };
+
+        let names = frame_names(asset.0);
+        let mut images: Vec<(String, RgbaImage)> = names
+            .iter()
+            .enumerate()
+            .map(|(index, name)| {
+                let image = file.frame(index as u32).image();
+                (name.clone(), image)
+            })
+            .collect();
+
+        let mut layers = Vec::new();
+        for layer in file.layers() {
+            let info = extras.layers.get(layer.id() as usize);
+            if info.is_some_and(|info| info.group) {
+                continue;
+            }
+
+            let layer_names = if settings.split_layers {
+                frame_names(&format!("{}_{}", asset.0, layer.name()))
+            } else {
+                Vec::new()
+            };
+            for (index, name) in layer_names.iter().enumerate() {
+                let image = layer.frame(index as u32).image();
+                images.push((name.clone(), image));
+            }
+
+            layers.push((
+                SpriteLayer {
+                    name: layer.name().to_owned(),
+                    visible: layer.is_visible(),
+                    opacity: layer.opacity() as f32 / 255.,
+                    blend_mode: layer.blend_mode(),
+                    user_data: info.and_then(|info| info.user_data.clone()),
+                    frames: Vec::new(),
+                },
+                layer_names,
+            ));
+        }
+
+        let pages = pack_images(images, &settings.atlas)?;
+        let (_, textures) = self.add_atlas(&pages)?;
+
+        let frames = names.iter().map(|name| textures[name]).collect();
+        let layers = layers
+            .into_iter()
+            .map(|(layer, names)| SpriteLayer {
+                frames: names.iter().map(|name| textures[name]).collect(),
+                ..layer
+            })
+            .collect();
+        let durations = (0..file.num_frames())
+            .map(|index| file.frame(index).duration() as f32 / 1000.)
+            .collect();
+        let tags = (0..file.num_tags())
+            .map(|id| {
+                let tag = file.tag(id);
+                AnimationTag {
+                    name: tag.name().to_owned(),
+                    from: tag.from_frame() as usize,
+                    to: tag.to_frame() as usize,
+                    direction: tag.animation_direction().into(),
+                }
+            })
+            .collect();
+
+        Ok(SpriteSheet {
+            frames,
+            durations,
+            tags,
+            layers,
+            slices: extras.slices,
+        })
+    }
+}
+
+#[derive(Debug, Default)]
+struct LayerInfo {
+    group: bool,
+    user_data: Option<UserData>,
+}
+
+/// What `asefile` doesn't read from a file
+#[derive(Debug, Default)]
+struct AsepriteExtras {
+    /// By layer id
+    layers: Vec<LayerInfo>,
+    slices: Vec<SpriteSlice>,
+}
+
+/// User data chunks belong to the layer or slice chunk right before them
+enum UserDataOwner {
+    Layer(usize),
+    Slice(usize),
+}
+
+fn read_extras(bytes: &[u8]) -> Result<AsepriteExtras, SmolError> {
+    let mut extras = AsepriteExtras::default();
+    let mut file = Reader::new(bytes);
+
+    file.u32()?;
+    if file.u16()? != FILE_MAGIC {
+        return Err(SmolError::new("Not an aseprite file"));
+    }
+    let frames = file.u16()?;
+    file.position = HEADER_SIZE;
+
+    let mut owner = None;
+    for _ in 0..frames {
+        let frame_start = file.position;
+        let frame_end = frame_start + file.u32()? as usize;
+        // magic number, chunk counts and duration
+        file.take(12)?;
+
+        while file.position + 6 <= frame_end {
+            let chunk_size = file.u32()? as usize;
+            let chunk_type = file.u16()?;
+            let mut chunk = Reader::new(file.take(chunk_size.saturating_sub(6))?);
+
+            owner = match chunk_type {
+                LAYER_CHUNK => {
+                    chunk.u16()?;
+                    extras.layers.push(LayerInfo {
+                        group: chunk.u16()? == 1,
+                        user_data: None,
+                    });
+                    Some(UserDataOwner::Layer(extras.layers.len() - 1))
+                }
+                SLICE_CHUNK => {
+                    extras.slices.push(read_slice(&mut chunk)?);
+                    Some(UserDataOwner::Slice(extras.slices.len() - 1))
+                }
+                USER_DATA_CHUNK => {
+                    let user_data = Some(read_user_data(&mut chunk)?);
+                    match owner {
+                        Some(UserDataOwner::Layer(index)) => {
+                            extras.layers[index].user_data = user_data
+                        }
+                        Some(UserDataOwner::Slice(index)) => {
+                            extras.slices[index].user_data = user_data
+                        }
+                        None => {}
+                    }
+                    None
+                }
+                _ => None,
+            };
+        }
+        file.position = frame_end;
+    }
+
+    Ok(extras)
+}
+
+fn read_slice(chunk: &mut Reader) -> Result<SpriteSlice, SmolError> {
+    let key_count = chunk.u32()?;
+    let flags = chunk.u32()?;
+    chunk.u32()?;
+    let name = chunk.string()?;
+
+    let mut keys = Vec::with_capacity(key_count as usize);
+    for _ in 0..key_count {
+        let from_frame = chunk.u32()? as usize;
+        let bounds = chunk.rectangle()?;
+        let center = if flags & 1 != 0 {
+            Some(chunk.rectangle()?)
+        } else {
+            None
+        };
+        let pivot = if flags & 2 != 0 {
+            Some(Vector::from([chunk.i32()? as f32, chunk.i32()? as f32]))
+        } else {
+            None
+        };
+
+        keys.push(SliceKey {
+            from_frame,
+            bounds,
+            center,
+            pivot,
+        });
+    }
+
+    Ok(SpriteSlice {
+        name,
+        keys,
+        user_data: None,
+    })
+}
+
+fn read_user_data(chunk: &mut Reader) -> Result<UserData, SmolError> {
+    let flags = chunk.u32()?;
+    let text = if flags & 1 != 0 {
+        Some(chunk.string()?)
+    } else {
+        None
+    };
+    let color = if flags & 2 != 0 {
+        let [r, g, b, a] = [chunk.u8()?, chunk.u8()?, chunk.u8()?, chunk.u8()?];
+        Some(Color(r, g, b, a as f32 / 255.))
+    } else {
+        None
+    };
+
+    Ok(UserData { text, color })
+}
+
+/// Reads the little endian values of an aseprite file
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Reader { bytes, position: 0 }
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], SmolError> {
+        let bytes = self
+            .bytes
+            .get(self.position..self.position + len)
+            .ok_or_else(|| SmolError::new("Aseprite file ended unexpectedly"))?;
+        self.position += len;
+
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, SmolError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, SmolError> {
+        let bytes = self.take(2)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u32(&mut self) -> Result<u32, SmolError> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn i32(&mut self) -> Result<i32, SmolError> {
+        Ok(self.u32()? as i32)
+    }
+
+    fn string(&mut self) -> Result<String, SmolError> {
+        let len = self.u16()? as usize;
+        Ok(String::from_utf8_lossy(self.take(len)?).into_owned())
+    }
+
+    /// x and y as signed and width and height as unsigned ints
+    fn rectangle(&mut self) -> Result<Rectangle, SmolError> {
+        Ok(Rectangle {
+            x: self.i32()? as f32,
+            y: self.i32()? as f32,
+            width: self.u32()? as f32,
+            height: self.u32()? as f32,
+        })
+    }
+}
@@ -1,17 +1,24 @@
+mod atlas;
+mod hot_reload;
+
+pub(crate) use atlas::{atlas_textures, pack_atlas, pack_images, read_packed_atlas, AtlasPage};
+pub use atlas::{AtlasSettings, PackedTexture};
+
 use crate::gfx::GfxContext;
 use crate::math::Vector2;
-use crate::renderer::{Font, Texture};
+use crate::renderer::bitmap_font::BitmapFont;
+use crate::renderer::shader::Shader;
+use crate::renderer::text::FontFamily;
+use crate::renderer::{Font, FontKind, Texture, DEFAULT_FONT_SIZE};
 use crate::{errors::SmolError, App};
-use hashbrown::HashMap;
-use image::GenericImageView;
+use hashbrown::{HashMap, HashSet};
+use hot_reload::{Watch, WatchedAsset};
 use nalgebra::Vector;
-use serde::Deserialize;
-use texture_packer::{
-    exporter::ImageExporter, importer::ImageImporter, TexturePacker, 
-    TexturePackerConfig,
-};
+use std::borrow::Cow;
+use std::fs;
+use std::path::{Path, PathBuf};
 
-use glyph_brush::{ab_glyph::*, *};
+use glyph_brush::ab_glyph::*;
 
 #[macro_export]
 macro_rules! import_file {
@@ -29,56 +36,385 @@ macro_rules! import_file {
     };
 }
 
+/// Embeds a file of the `assets` directory next to the game's `Cargo.toml` with the
+/// `embed-assets` feature, or reads it from `AppSettings::asset_root` at runtime without it,
+/// so release builds can embed while artists iterate on the files. Files are always embedded
+/// from `assets`, so `asset_root` has to hold the same files for both builds to match.
+/// Embedded files are also what the `load_*_from_path` functions load with the feature.
+///
+/// `let player = asset!(app, "player.png")?;` then `app.load_texture(player.as_asset())`
+#[cfg(feature = "embed-assets")]
+#[macro_export]
+macro_rules! asset {
+    ($app:expr, $path:expr) => {
+        Ok::<_, $crate::errors::SmolError>($app.embed_asset(
+            $path,
+            include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/assets/", $path)),
+        ))
+    };
+}
+
+/// Embeds a file of the `assets` directory next to the game's `Cargo.toml` with the
+/// `embed-assets` feature, or reads it from `AppSettings::asset_root` at runtime without it,
+/// so release builds can embed while artists iterate on the files. Files are always embedded
+/// from `assets`, so `asset_root` has to hold the same files for both builds to match.
+/// Embedded files are also what the `load_*_from_path` functions load with the feature.
+///
+/// `let player = asset!(app, "player.png")?;` then `app.load_texture(player.as_asset())`
+#[cfg(not(feature = "embed-assets"))]
+#[macro_export]
+macro_rules! asset {
+    ($app:expr, $path:expr) => {
+        $app.read_asset($path)
+    };
+}
+
 pub type Asset<'a> = (&'a str, &'a str, &'a [u8]);
 
-#[derive(Deserialize, Debug)]
-pub struct PackedTexture {
-    width: u32,
-    height: u32,
-    x: u32,
-    y: u32,
+/// Owned version of an `Asset`, for files read at runtime
+#[derive(Debug, Clone)]
+pub struct AssetFile {
+    pub name: String,
+    pub extension: String,
+    pub bytes: Cow<'static, [u8]>,
+}
+
+impl AssetFile {
+    /// Reads a file, named after its file name without the extension like `import_file!`
+    pub fn read<P: AsRef<Path>>(path: P) -> Result<Self, SmolError> {
+        let path = path.as_ref();
+        let bytes = fs::read(path).map_err(|e| {
+            SmolError::new(format!("Couldn't read asset {}: {}", path.display(), e))
+        })?;
+        let (name, extension) = split_file_name(path);
+
+        Ok(AssetFile {
+            name,
+            extension,
+            bytes: Cow::Owned(bytes),
+        })
+    }
+
+    pub fn embedded(path: &str, bytes: &'static [u8]) -> Self {
+        let (name, extension) = split_file_name(Path::new(path));
+
+        AssetFile {
+            name,
+            extension,
+            bytes: Cow::Borrowed(bytes),
+        }
+    }
+
+    pub fn as_asset(&self) -> Asset<'_> {
+        (&self.name, &self.extension, &self.bytes)
+    }
+
+    pub(crate) fn text(&self) -> Result<&str, SmolError> {
+        std::str::from_utf8(&self.bytes).map_err(|_| {
+            SmolError::new(format!(
+                "Asset {}.{} isn't UTF-8 text",
+                self.name, self.extension
+            ))
+        })
+    }
+}
+
+fn split_file_name(path: &Path) -> (String, String) {
+    let name = path
+        .file_stem()
+        .map_or_else(String::new, |name| name.to_string_lossy().into_owned());
+    let extension = path.extension().map_or_else(String::new, |extension| {
+        extension.to_string_lossy().into_owned()
+    });
+
+    (name, extension)
 }
 
 #[derive(Default)]
 pub struct AssetStore {
     textures: HashMap<String, Texture>,
     fonts: HashMap<String, Font>,
+    /// Names using each texture id, frames of an atlas share its texture
+    texture_refs: HashMap<u32, usize>,
+    /// Directory paths of runtime loaded assets are relative to
+    root: PathBuf,
+    /// Assets loaded from paths are reloaded when their files change
+    hot_reload: bool,
+    watches: Vec<Watch>,
+    /// Seconds since files were last checked for changes
+    poll_timer: f32,
+    /// Files that failed to reload while polling, see `App::take_reload_errors`
+    reload_errors: Vec<(PathBuf, SmolError)>,
+    /// Files embedded by `asset!`, by their path in the asset root
+    #[cfg(feature = "embed-assets")]
+    embedded: HashMap<PathBuf, AssetFile>,
+}
+
+impl AssetStore {
+    pub fn new<P: Into<PathBuf>>(root: P, hot_reload: bool) -> Self {
+        AssetStore {
+            textures: HashMap::new(),
+            fonts: HashMap::new(),
+            texture_refs: HashMap::new(),
+            root: root.into(),
+            hot_reload,
+            watches: Vec::new(),
+            poll_timer: 0.,
+            reload_errors: Vec::new(),
+            #[cfg(feature = "embed-assets")]
+            embedded: HashMap::new(),
+        }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+}
+
+impl Drop for AssetStore {
+    fn drop(&mut self) {
+        for id in self.texture_refs.keys() {
+            GfxContext::delete_texture(*id);
+        }
+    }
 }
 
 impl App {
+    /// Reads a file from the asset root at runtime, see `asset!` to embed it in release builds.
+    /// With the `embed-assets` feature nothing is read from disk, the file has to be embedded
+    /// by `asset!` first
+    pub fn read_asset(&self, path: &str) -> Result<AssetFile, SmolError> {
+        #[cfg(feature = "embed-assets")]
+        return self
+            .asset_store
+            .embedded
+            .get(Path::new(path))
+            .cloned()
+            .ok_or_else(|| {
+                SmolError::new(format!(
+                    "Asset {} isn't embedded, embed it with `asset!` before loading it",
+                    path
+                ))
+            });
+
+        #[cfg(not(feature = "embed-assets"))]
+        AssetFile::read(self.asset_path(path))
+    }
+
+    /// Keeps a file embedded by `asset!` so it's also found by its path
+    #[cfg(feature = "embed-assets")]
+    pub fn embed_asset(&mut self, path: &str, bytes: &'static [u8]) -> AssetFile {
+        let file = AssetFile::embedded(path, bytes);
+        self.asset_store
+            .embedded
+            .insert(PathBuf::from(path), file.clone());
+
+        file
+    }
+
+    fn asset_path(&self, path: &str) -> PathBuf {
+        self.asset_store.root.join(path)
+    }
+
+    /// Loads a texture from the asset root, it's uploaded again when the file changes while hot
+    /// reloading, `.aseprite` files use their first frame
+    pub fn load_texture_from_path(&mut self, path: &str) -> Result<Texture, SmolError> {
+        let file = self.read_asset(path)?;
+        let texture = if file.extension == "aseprite" {
+            self.load_single_aseprite_texture(file.as_asset())?
+        } else {
+            self.load_texture(file.as_asset())?
+        };
+        self.watch_asset(WatchedAsset::Texture(texture), vec![self.asset_path(path)]);
+
+        Ok(texture)
+    }
+
+    pub fn load_font_from_path(&mut self, path: &str) -> Result<Font, SmolError> {
+        let file = self.read_asset(path)?;
+        let font = self.load_font(file.as_asset())?;
+        self.watch_asset(WatchedAsset::Font(font), vec![self.asset_path(path)]);
+
+        Ok(font)
+    }
+
+    pub fn load_sdf_font_from_path(&mut self, path: &str) -> Result<Font, SmolError> {
+        let file = self.read_asset(path)?;
+        let font = self.load_sdf_font(file.as_asset())?;
+        self.watch_asset(WatchedAsset::Font(font), vec![self.asset_path(path)]);
+
+        Ok(font)
+    }
+
+    /// Loads a BMFont from the asset root, its pages are read from the descriptor's directory
+    pub fn load_bitmap_font_from_path(&mut self, path: &str) -> Result<Font, SmolError> {
+        let descriptor = self.read_asset(path)?;
+        let directory = Path::new(path).parent().unwrap_or_else(|| Path::new(""));
+        let pages = BitmapFont::page_files(&descriptor.bytes)?
+            .iter()
+            .map(|page| self.read_asset(&directory.join(page).to_string_lossy()))
+            .collect::<Result<Vec<_>, _>>()?;
+        let pages: Vec<Asset> = pages.iter().map(AssetFile::as_asset).collect();
+
+        self.load_bitmap_font(descriptor.as_asset(), &pages)
+    }
+
+    pub fn load_locale_from_path(&mut self, path: &str) -> Result<(), SmolError> {
+        let file = self.read_asset(path)?;
+        self.load_locale(file.as_asset())
+    }
+
     pub fn load_font<'a>(&mut self, asset: Asset<'a>) -> Result<Font, SmolError> {
+        self.load_font_with_size(asset, DEFAULT_FONT_SIZE)
+    }
+
+    /// Loads a font drawn at `size` pixels unless `TextSettings::size` says otherwise
+    pub fn load_font_with_size<'a>(
+        &mut self,
+        asset: Asset<'a>,
+        size: f32,
+    ) -> Result<Font, SmolError> {
         let b = asset.2.to_vec();
-        let font = FontArc::try_from_vec(b)?;
-        let glyph_brush: GlyphBrush<[f32; 13]> = GlyphBrushBuilder::using_font(font).build();
-        let dimensions = glyph_brush.texture_dimensions();
-        let texture_id = GfxContext::generate_font_texture(dimensions);
-        let size = Vector::from([dimensions.0 as f32, dimensions.1 as f32]);
-        let texture = Texture::new(texture_id, size, Vector2::default(), size);
-        let font = Font {
-            id: self.renderer.glyph_brushs.len(),
-            texture,
-        };
+        let font_data = FontArc::try_from_vec(b)?;
+        let font = self
+            .renderer
+            .font_cache
+            .next_brush_font(FontKind::Vector, size);
         self.insert_font(asset.0, font)?;
-        self.renderer.glyph_brushs.insert(font, glyph_brush);
+        self.renderer.font_cache.add_font(font_data);
 
         Ok(font)
     }
 
-    pub fn insert_font(&mut self, name: &str, font: Font) -> Result<(), SmolError> {
-        if self.asset_store.textures.contains_key(name) {
+    /// Loads a font drawn from signed distance fields, so it stays sharp at any size or zoom and
+    /// can have outlines, glows and shadows through `TextSettings`
+    pub fn load_sdf_font<'a>(&mut self, asset: Asset<'a>) -> Result<Font, SmolError> {
+        self.load_sdf_font_with_size(asset, DEFAULT_FONT_SIZE)
+    }
+
+    pub fn load_sdf_font_with_size<'a>(
+        &mut self,
+        asset: Asset<'a>,
+        size: f32,
+    ) -> Result<Font, SmolError> {
+        let font_data = FontArc::try_from_vec(asset.2.to_vec())?;
+        let font = self
+            .renderer
+            .font_cache
+            .next_brush_font(FontKind::Sdf, size);
+        self.insert_font(asset.0, font)?;
+        self.renderer.font_cache.add_sdf_font(font_data);
+
+        Ok(font)
+    }
+
+    /// Draws characters missing from the family's first font with the first of its fallbacks
+    /// that has them, wherever that font is used. The first font is stored under `name` and
+    /// returned
+    pub fn add_font_family(&mut self, name: &str, family: FontFamily) -> Result<Font, SmolError> {
+        let font = *family
+            .fonts
+            .first()
+            .ok_or_else(|| SmolError::new("Font family needs at least one font"))?;
+        if family
+            .fonts
+            .iter()
+            .any(|fallback| fallback.kind != font.kind || fallback.kind == FontKind::Bitmap)
+        {
             return Err(SmolError::new(
-                "Asset store already has a texture with this name",
+                "Font family fonts must all be vector fonts or all be SDF fonts",
             ));
         }
+
+        self.insert_font(name, font)?;
+        self.renderer
+            .font_cache
+            .fallbacks
+            .insert(font.id, family.fonts.iter().map(|font| font.id).collect());
+
+        Ok(font)
+    }
+
+    /// Loads an AngelCode BMFont from its `.fnt` descriptor, in the text or XML format, and the
+    /// page images it names. The font is drawn at the size it was exported at unless
+    /// `TextSettings::size` says otherwise
+    pub fn load_bitmap_font<'a>(
+        &mut self,
+        descriptor: Asset<'a>,
+        pages: &[Asset<'a>],
+    ) -> Result<Font, SmolError> {
+        self.check_font_name(descriptor.0)?;
+        let bitmap_font = BitmapFont::from_bmfont(descriptor.2, pages)?;
+        self.add_bitmap_font(descriptor.0, bitmap_font)
+    }
+
+    /// Loads a font from an image split into a grid of `cell_size` pixel cells, one for each of
+    /// `chars` read left to right and top to bottom
+    pub fn load_grid_font<'a>(
+        &mut self,
+        image: Asset<'a>,
+        cell_size: Vector2<u32>,
+        chars: &str,
+    ) -> Result<Font, SmolError> {
+        self.check_font_name(image.0)?;
+        let cell_size = Vector::from([cell_size.x as f32, cell_size.y as f32]);
+        let bitmap_font = BitmapFont::from_grid(image, cell_size, chars)?;
+
+        self.add_bitmap_font(image.0, bitmap_font)
+    }
+
+    fn add_bitmap_font(&mut self, name: &str, bitmap_font: BitmapFont) -> Result<Font, SmolError> {
+        let font = self
+            .renderer
+            .font_cache
+            .next_bitmap_font(bitmap_font.line_height);
+        self.insert_font(name, font)?;
+        self.renderer.font_cache.add_bitmap_font(bitmap_font);
+
+        Ok(font)
+    }
+
+    pub fn insert_font(&mut self, name: &str, font: Font) -> Result<(), SmolError> {
+        self.check_font_name(name)?;
         self.asset_store.fonts.insert(name.to_owned(), font);
 
         Ok(())
     }
 
+    /// Fails like `insert_font` would, checked before bitmap fonts upload their images so a
+    /// name that's taken doesn't leave textures behind that nothing can free
+    fn check_font_name(&self, name: &str) -> Result<(), SmolError> {
+        if self.asset_store.fonts.contains_key(name) {
+            return Err(SmolError::new(
+                "Asset store already has a font with this name",
+            ));
+        }
+
+        Ok(())
+    }
+
     pub fn get_font(&self, name: &str) -> Option<&Font> {
         self.asset_store.fonts.get(name)
     }
 
+    /// Fails like `insert_texture` would for any of `names`, checked before uploading so a name
+    /// that's taken doesn't leave a texture behind that nothing can free
+    fn check_texture_names<'n>(
+        &self,
+        names: impl IntoIterator<Item = &'n str>,
+    ) -> Result<(), SmolError> {
+        let mut new_names = HashSet::new();
+        for name in names {
+            if self.asset_store.textures.contains_key(name) || !new_names.insert(name) {
+                return Err(SmolError::new(
+                    "Asset store already has a texture with this name",
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
     pub fn insert_texture(&mut self, name: &str, texture: Texture) -> Result<(), SmolError> {
         if self.asset_store.textures.contains_key(name) {
             return Err(SmolError::new(
@@ -86,6 +422,49 @@ impl App {
             ));
         }
         self.asset_store.textures.insert(name.to_owned(), texture);
+        *self.asset_store.texture_refs.entry(texture.id).or_insert(0) += 1;
+
+        Ok(())
+    }
+
+    /// Removes a texture by name, its GL texture is deleted once no other name uses it, so an
+    /// atlas is freed with its last frame. Copies of the texture are skipped when drawn after
+    /// that, see `Texture::is_alive`
+    pub fn unload_texture(&mut self, name: &str) -> Result<(), SmolError> {
+        let texture =
+            self.asset_store.textures.remove(name).ok_or_else(|| {
+                SmolError::new(format!("Asset store has no texture named {}", name))
+            })?;
+
+        let refs = self.asset_store.texture_refs.entry(texture.id).or_insert(1);
+        *refs -= 1;
+        if *refs == 0 {
+            self.asset_store.texture_refs.remove(&texture.id);
+            // sprites already batched with the texture are drawn before it's deleted
+            self.renderer.flush_batch();
+            GfxContext::delete_texture(texture.id);
+            self.asset_store.unwatch_texture(texture.id);
+        }
+
+        Ok(())
+    }
+
+    /// Removes a font by name, it's freed once no other name uses it and its slot is reused by
+    /// the next font loaded. Copies of the font are skipped when drawn after that
+    pub fn unload_font(&mut self, name: &str) -> Result<(), SmolError> {
+        let font = self
+            .asset_store
+            .fonts
+            .remove(name)
+            .ok_or_else(|| SmolError::new(format!("Asset store has no font named {}", name)))?;
+
+        if self.asset_store.fonts.values().any(|other| *other == font) {
+            return Ok(());
+        }
+
+        self.renderer.flush_batch();
+        self.renderer.font_cache.unload_font(font);
+        self.asset_store.unwatch_font(font);
 
         Ok(())
     }
@@ -99,7 +478,8 @@ impl App {
     }
 
     pub fn load_texture<'a>(&mut self, asset: Asset<'a>) -> Result<Texture, SmolError> {
-        let (width, height, id) = GfxContext::generate_texture(asset.2, "");
+        self.check_texture_names([asset.0])?;
+        let (width, height, id) = GfxContext::generate_texture(asset.2, "")?;
         let size = Vector::from([width as f32, height as f32]);
         let texture = Texture::new(id, size, Vector2::default(), size);
         self.insert_texture(&asset.0, texture)?;
@@ -110,7 +490,8 @@ impl App {
         &mut self,
         asset: Asset<'a>,
     ) -> Result<Texture, SmolError> {
-        let (width, height, id) = GfxContext::generate_texture(asset.2, "aseprite");
+        self.check_texture_names([asset.0])?;
+        let (width, height, id) = GfxContext::generate_texture(asset.2, "aseprite")?;
         let size = Vector::from([width as f32, height as f32]);
         let texture = Texture::new(id, size, Vector2::default(), size);
         self.insert_texture(&asset.0, texture)?;
@@ -121,43 +502,143 @@ impl App {
         &mut self,
         texture_assets: Vec<&'a Asset>,
     ) -> Result<HashMap<String, Texture>, SmolError> {
-        let texture_packer_config = TexturePackerConfig {
-            max_width: std::u32::MAX,
-            max_height: std::u32::MAX,
-            allow_rotation: false,
-            texture_outlines: false,
-            border_padding: 0,
-            texture_padding: 0,
-            trim: false,
-            ..Default::default()
-        };
+        self.load_into_texture_atlas_with_settings(texture_assets, &AtlasSettings::default())
+    }
 
-        let mut textures: HashMap<String, Texture> = HashMap::default();
+    /// Packs images into as few textures as `settings` allow, see `AtlasSettings`
+    pub fn load_into_texture_atlas_with_settings(
+        &mut self,
+        texture_assets: Vec<&Asset>,
+        settings: &AtlasSettings,
+    ) -> Result<HashMap<String, Texture>, SmolError> {
+        let pages = pack_atlas(&texture_assets, settings)?;
+        let (_, textures) = self.add_atlas(&pages)?;
 
-        let mut packer = TexturePacker::new_skyline(texture_packer_config);
+        Ok(textures)
+    }
 
-        for asset in texture_assets {
-            let image = ImageImporter::import_from_memory(asset.2).unwrap();
-            let _ = packer.pack_own(asset.0.clone(), image);
-        }
-        let image = ImageExporter::export(&packer).unwrap();
-        let atlas_width = image.width();
-        let atlas_height = image.height();
-        let (_, _, id) = GfxContext::generate_texture(image.as_bytes(), "");
-
-        for (name, frame) in packer.get_frames() {
-            let pos = Vector::from([frame.frame.x as f32, frame.frame.y as f32]);
-            let size = Vector::from([frame.frame.w as f32, frame.frame.h as f32]);
-            let texture = Texture::new(
-                id,
-                size,
-                pos,
-                Vector::from([atlas_width as f32, atlas_height as f32]),
+    /// Uploads the pages of a packed atlas and stores the textures of its images by name,
+    /// returning the ids of the pages too
+    pub(crate) fn add_atlas(
+        &mut self,
+        pages: &[AtlasPage],
+    ) -> Result<(Vec<u32>, HashMap<String, Texture>), SmolError> {
+        self.check_texture_names(
+            pages
+                .iter()
+                .flat_map(|page| page.frames.iter().map(|frame| frame.name.as_str())),
+        )?;
+
+        let mut ids = Vec::with_capacity(pages.len());
+        let mut textures: HashMap<String, Texture> = HashMap::default();
+        for page in pages {
+            let id = GfxContext::generate_empty_texture(
+                page.image.width as _,
+                page.image.height as _,
+                page.image.data.as_ptr() as _,
             );
-            self.insert_texture(name, texture)?;
-            textures.insert(name.to_string(), texture);
+            ids.push(id);
+
+            for (name, texture) in atlas_textures(id, page) {
+                self.insert_texture(&name, texture)?;
+                textures.insert(name, texture);
+            }
         }
 
+        Ok((ids, textures))
+    }
+
+    /// Packs images read from the asset root into one texture, it's repacked when any of them
+    /// change while hot reloading
+    pub fn load_into_texture_atlas_from_paths(
+        &mut self,
+        paths: &[&str],
+    ) -> Result<HashMap<String, Texture>, SmolError> {
+        self.load_into_texture_atlas_from_paths_with_settings(paths, &AtlasSettings::default())
+    }
+
+    pub fn load_into_texture_atlas_from_paths_with_settings(
+        &mut self,
+        paths: &[&str],
+        settings: &AtlasSettings,
+    ) -> Result<HashMap<String, Texture>, SmolError> {
+        let files = paths
+            .iter()
+            .map(|path| self.read_asset(path))
+            .collect::<Result<Vec<_>, _>>()?;
+        let assets: Vec<Asset> = files.iter().map(AssetFile::as_asset).collect();
+        let pages = pack_atlas(&assets.iter().collect::<Vec<_>>(), settings)?;
+        let (ids, textures) = self.add_atlas(&pages)?;
+
+        let paths = paths.iter().map(|path| self.asset_path(path)).collect();
+        self.watch_asset(
+            WatchedAsset::Atlas {
+                pages: ids,
+                settings: *settings,
+            },
+            paths,
+        );
+
         Ok(textures)
     }
+
+    /// Loads an atlas packed ahead of time, like with TexturePacker, instead of packing its
+    /// images every launch. See `PackedTexture` for the RON descriptor format, TexturePacker's
+    /// JSON hash and array exports are read too
+    pub fn load_packed_atlas<'a>(
+        &mut self,
+        image: Asset<'a>,
+        descriptor: Asset<'a>,
+    ) -> Result<HashMap<String, Texture>, SmolError> {
+        let page = read_packed_atlas(&image, &descriptor)?;
+        let (_, textures) = self.add_atlas(&[page])?;
+
+        Ok(textures)
+    }
+
+    /// Loads a packed atlas from the asset root, it's uploaded again when the image or the
+    /// descriptor change while hot reloading
+    pub fn load_packed_atlas_from_path(
+        &mut self,
+        image_path: &str,
+        descriptor_path: &str,
+    ) -> Result<HashMap<String, Texture>, SmolError> {
+        let image = self.read_asset(image_path)?;
+        let descriptor = self.read_asset(descriptor_path)?;
+        let page = read_packed_atlas(&image.as_asset(), &descriptor.as_asset())?;
+        let (ids, textures) = self.add_atlas(&[page])?;
+
+        let paths = vec![
+            self.asset_path(image_path),
+            self.asset_path(descriptor_path),
+        ];
+        self.watch_asset(WatchedAsset::PackedAtlas(ids[0]), paths);
+
+        Ok(textures)
+    }
+
+    /// Compiles a shader for `Renderer::set_shader` from GLSL sources, usually a custom fragment
+    /// shader with `DEFAULT_VERTEX_SHADER`
+    pub fn load_shader(&mut self, vertex: &str, fragment: &str) -> Result<Shader, SmolError> {
+        let id = GfxContext::build_shader(None, vertex, fragment)?;
+
+        Ok(Shader::new(id))
+    }
+
+    /// Compiles a shader from GLSL files of the asset root, it's rebuilt when they change while
+    /// hot reloading
+    pub fn load_shader_from_path(
+        &mut self,
+        vertex_path: &str,
+        fragment_path: &str,
+    ) -> Result<Shader, SmolError> {
+        let vertex = self.read_asset(vertex_path)?;
+        let fragment = self.read_asset(fragment_path)?;
+        let shader = self.load_shader(vertex.text()?, fragment.text()?)?;
+
+        let paths = vec![self.asset_path(vertex_path), self.asset_path(fragment_path)];
+        self.watch_asset(WatchedAsset::Shader(shader), paths);
+
+        Ok(shader)
+    }
 }
@@ -0,0 +1,425 @@
+use std::collections::HashMap as SerdeMap;
+
+use image::{imageops, RgbaImage};
+use nalgebra::Vector;
+use serde::Deserialize;
+use texture_packer::{
+    exporter::ImageExporter, importer::ImageImporter, MultiTexturePacker, TexturePackerConfig,
+};
+
+use super::Asset;
+use crate::errors::SmolError;
+use crate::gfx::{GfxContext, ImageData};
+use crate::math::Vector2;
+use crate::renderer::Texture;
+
+/// How images are laid out in the pages of a texture atlas
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AtlasSettings {
+    /// Largest width and height of a page, images that don't fit spill into more pages. It's
+    /// capped to the biggest texture the GPU supports
+    pub max_page_size: Vector2<u32>,
+    /// Transparent pixels between images and around the edges of a page
+    pub padding: u32,
+    /// Pixels the edges of every image are repeated outwards by, so filtering near an edge
+    /// doesn't pick up its neighbours
+    pub extrude: u32,
+    /// Cuts the transparent border off images, they're still drawn in the same place, see
+    /// `Texture::offset`
+    pub trim: bool,
+    /// Lets images be turned 90 degrees to fit more of them in a page
+    pub allow_rotation: bool,
+}
+
+impl Default for AtlasSettings {
+    fn default() -> Self {
+        AtlasSettings {
+            max_page_size: Vector::from([4096, 4096]),
+            padding: 2,
+            extrude: 1,
+            trim: false,
+            allow_rotation: false,
+        }
+    }
+}
+
+/// Region of an atlas packed ahead of time, RON descriptors map names to them:
+///
+/// ```ron
+/// {
+///     "player": (x: 0, y: 0, width: 16, height: 24),
+///     "coin": (x: 16, y: 0, width: 6, height: 6, offset: (5, 5), source_size: Some((16, 16))),
+/// }
+/// ```
+#[derive(Deserialize, Debug, Clone, Copy)]
+pub struct PackedTexture {
+    pub x: u32,
+    pub y: u32,
+    /// Size of the image, it takes up `height` by `width` pixels of the atlas when `rotated`
+    pub width: u32,
+    pub height: u32,
+    /// Stored turned 90 degrees clockwise
+    #[serde(default)]
+    pub rotated: bool,
+    /// Where a trimmed image starts in the original one
+    #[serde(default)]
+    pub offset: (u32, u32),
+    /// Size of the image before it was trimmed
+    #[serde(default)]
+    pub source_size: Option<(u32, u32)>,
+}
+
+/// TexturePacker's JSON export, either the hash or the array flavour
+#[derive(Deserialize)]
+struct TexturePackerAtlas {
+    frames: TexturePackerFrames,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum TexturePackerFrames {
+    Hash(SerdeMap<String, TexturePackerFrame>),
+    Array(Vec<TexturePackerFrame>),
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TexturePackerFrame {
+    /// Only set in the array flavour, the hash one uses it as the key
+    #[serde(default)]
+    filename: String,
+    frame: TexturePackerRect,
+    #[serde(default)]
+    rotated: bool,
+    sprite_source_size: Option<TexturePackerRect>,
+    source_size: Option<TexturePackerSize>,
+}
+
+#[derive(Deserialize)]
+struct TexturePackerRect {
+    x: u32,
+    y: u32,
+    w: u32,
+    h: u32,
+}
+
+#[derive(Deserialize)]
+struct TexturePackerSize {
+    w: u32,
+    h: u32,
+}
+
+impl From<TexturePackerFrame> for PackedTexture {
+    fn from(frame: TexturePackerFrame) -> Self {
+        let offset = frame
+            .sprite_source_size
+            .map_or((0, 0), |rect| (rect.x, rect.y));
+
+        PackedTexture {
+            x: frame.frame.x,
+            y: frame.frame.y,
+            width: frame.frame.w,
+            height: frame.frame.h,
+            rotated: frame.rotated,
+            offset,
+            source_size: frame.source_size.map(|size| (size.w, size.h)),
+        }
+    }
+}
+
+/// Where an image ended up in an atlas page
+#[derive(Debug, Clone)]
+pub(crate) struct AtlasFrame {
+    pub name: String,
+    /// x, y, width and height in the page, as packed so turned when `rotated`
+    pub rect: [u32; 4],
+    pub rotated: bool,
+    /// Where the trimmed image starts in the original one
+    pub offset: [u32; 2],
+    pub source_size: [u32; 2],
+}
+
+pub(crate) struct AtlasPage {
+    pub image: ImageData,
+    pub frames: Vec<AtlasFrame>,
+}
+
+/// Decodes images and packs them into atlas pages
+pub(crate) fn pack_atlas(
+    assets: &[&Asset],
+    settings: &AtlasSettings,
+) -> Result<Vec<AtlasPage>, SmolError> {
+    let images = assets
+        .iter()
+        .map(|asset| {
+            let image = ImageImporter::import_from_memory(asset.2)
+                .map_err(|e| SmolError::new(format!("Couldn't read {}: {}", asset.0, e)))?;
+            Ok((asset.0.to_owned(), image.to_rgba8()))
+        })
+        .collect::<Result<Vec<_>, SmolError>>()?;
+
+    pack_images(images, settings)
+}
+
+/// Packs decoded images into as many pages as they need, see `pack_atlas`
+pub(crate) fn pack_images(
+    images: Vec<(String, RgbaImage)>,
+    settings: &AtlasSettings,
+) -> Result<Vec<AtlasPage>, SmolError> {
+    let mut page_size = settings.max_page_size;
+    let max_texture_size = GfxContext::max_texture_size();
+    if max_texture_size > 0 {
+        page_size = page_size.map(|size| size.min(max_texture_size));
+    }
+
+    // trimming and extrusion are done here instead of by the packer so the offsets of trimmed
+    // images are known and the extruded edges are their own
+    let config = TexturePackerConfig {
+        max_width: page_size.x,
+        max_height: page_size.y,
+        allow_rotation: settings.allow_rotation,
+        border_padding: settings.padding,
+        texture_padding: settings.padding,
+        texture_extrusion: 0,
+        trim: false,
+        texture_outlines: false,
+    };
+    let mut packer = MultiTexturePacker::new_skyline(config);
+
+    let mut sources = Vec::with_capacity(images.len());
+    for (name, mut image) in images {
+        let source_size = [image.width(), image.height()];
+        let mut offset = [0, 0];
+        if settings.trim {
+            let [x, y, width, height] = opaque_bounds(&image);
+            image = imageops::crop_imm(&image, x, y, width, height).to_image();
+            offset = [x, y];
+        }
+        if settings.extrude > 0 {
+            image = extrude(&image, settings.extrude);
+        }
+
+        packer.pack_own(name.clone(), image).map_err(|_| {
+            SmolError::new(format!(
+                "{} doesn't fit in an atlas page of {}x{}",
+                name, page_size.x, page_size.y
+            ))
+        })?;
+        sources.push((name, offset, source_size));
+    }
+
+    let mut pages = Vec::with_capacity(packer.get_pages().len());
+    for page in packer.get_pages() {
+        let image = ImageExporter::export(page)
+            .map_err(SmolError::new)?
+            .to_rgba8();
+
+        let e = settings.extrude;
+        let frames = sources
+            .iter()
+            .filter_map(|(name, offset, source_size)| {
+                let frame = page.get_frame(name)?;
+                let rect = frame.frame;
+                Some(AtlasFrame {
+                    name: name.clone(),
+                    rect: [rect.x + e, rect.y + e, rect.w - e * 2, rect.h - e * 2],
+                    rotated: frame.rotated,
+                    offset: *offset,
+                    source_size: *source_size,
+                })
+            })
+            .collect();
+
+        pages.push(AtlasPage {
+            image: ImageData::from_rgba(image.width(), image.height(), image.into_raw()),
+            frames,
+        });
+    }
+
+    Ok(pages)
+}
+
+/// Reads an atlas packed ahead of time, `descriptor` is a RON map of `PackedTexture`s or a
+/// TexturePacker JSON export. Names lose their file extension, so `"enemies/bat.png"` is stored
+/// as `enemies/bat`
+pub(crate) fn read_packed_atlas(image: &Asset, descriptor: &Asset) -> Result<AtlasPage, SmolError> {
+    let regions: Vec<(String, PackedTexture)> = match descriptor.1 {
+        "ron" => {
+            let regions: SerdeMap<String, PackedTexture> = ron::de::from_bytes(descriptor.2)?;
+            regions.into_iter().collect()
+        }
+        "json" => {
+            let atlas: TexturePackerAtlas = serde_json::from_slice(descriptor.2)?;
+            match atlas.frames {
+                TexturePackerFrames::Hash(frames) => frames
+                    .into_iter()
+                    .map(|(name, frame)| (name, frame.into()))
+                    .collect(),
+                TexturePackerFrames::Array(frames) => frames
+                    .into_iter()
+                    .map(|frame| (frame.filename.clone(), frame.into()))
+                    .collect(),
+            }
+        }
+        extension => {
+            return Err(SmolError::new(format!(
+                "Atlas descriptors need to be .ron or .json files, not .{}",
+                extension
+            )))
+        }
+    };
+
+    let image = ImageImporter::import_from_memory(image.2)
+        .map_err(|e| SmolError::new(format!("Couldn't read {}: {}", image.0, e)))?
+        .to_rgba8();
+
+    let mut frames = Vec::with_capacity(regions.len());
+    for (name, region) in regions {
+        let (width, height) = if region.rotated {
+            (region.height, region.width)
+        } else {
+            (region.width, region.height)
+        };
+        let outside = |start: u32, size: u32, image_size: u32| {
+            start.checked_add(size).is_none_or(|end| end > image_size)
+        };
+        if outside(region.x, width, image.width()) || outside(region.y, height, image.height()) {
+            return Err(SmolError::new(format!(
+                "{} is outside of the {}x{} atlas image",
+                name,
+                image.width(),
+                image.height()
+            )));
+        }
+
+        frames.push(AtlasFrame {
+            name: strip_extension(&name).to_owned(),
+            rect: [region.x, region.y, width, height],
+            rotated: region.rotated,
+            offset: [region.offset.0, region.offset.1],
+            source_size: region
+                .source_size
+                .map_or([region.width, region.height], |(width, height)| {
+                    [width, height]
+                }),
+        });
+    }
+
+    Ok(AtlasPage {
+        image: ImageData::from_rgba(image.width(), image.height(), image.into_raw()),
+        frames,
+    })
+}
+
+fn strip_extension(name: &str) -> &str {
+    let file_start = name.rfind('/').map_or(0, |slash| slash + 1);
+    match name[file_start..].rfind('.') {
+        Some(dot) => &name[..file_start + dot],
+        None => name,
+    }
+}
+
+/// Textures of the images packed into page texture `id`
+pub(crate) fn atlas_textures(id: u32, page: &AtlasPage) -> Vec<(String, Texture)> {
+    let page_size = Vector::from([page.image.width as f32, page.image.height as f32]);
+
+    page.frames
+        .iter()
+        .map(|frame| {
+            let [x, y, mut width, mut height] = frame.rect;
+            if frame.rotated {
+                std::mem::swap(&mut width, &mut height);
+            }
+            let pos = Vector::from([x as f32, y as f32]);
+            let size = Vector::from([width as f32, height as f32]);
+            let offset = Vector::from([frame.offset[0] as f32, frame.offset[1] as f32]);
+            let source_size =
+                Vector::from([frame.source_size[0] as f32, frame.source_size[1] as f32]);
+
+            let texture =
+                Texture::new(id, size, pos, page_size).packed(frame.rotated, offset, source_size);
+            (frame.name.clone(), texture)
+        })
+        .collect()
+}
+
+/// x, y, width and height of the part of an image that isn't fully transparent, a fully
+/// transparent image keeps one pixel
+fn opaque_bounds(image: &RgbaImage) -> [u32; 4] {
+    let mut min = [u32::MAX, u32::MAX];
+    let mut max = [0, 0];
+    for (x, y, pixel) in image.enumerate_pixels() {
+        if pixel[3] != 0 {
+            min = [min[0].min(x), min[1].min(y)];
+            max = [max[0].max(x), max[1].max(y)];
+        }
+    }
+
+    if min[0] == u32::MAX {
+        return [0, 0, 1.min(image.width()), 1.min(image.height())];
+    }
+
+    [min[0], min[1], max[0] - min[0] + 1, max[1] - min[1] + 1]
+}
+
+/// Copy of an image with its edge pixels repeated `amount` pixels outwards
+fn extrude(image: &RgbaImage, amount: u32) -> RgbaImage {
+    let (width, height) = image.dimensions();
+    if width == 0 || height == 0 {
+        return image.clone();
+    }
+
+    RgbaImage::from_fn(width + amount * 2, height + amount * 2, |x, y| {
+        let x = x.saturating_sub(amount).min(width - 1);
+        let y = y.saturating_sub(amount).min(height - 1);
+        *image.get_pixel(x, y)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use image::Rgba;
+
+    use super::*;
+
+    const CLEAR: Rgba<u8> = Rgba([0, 0, 0, 0]);
+
+    #[test]
+    fn opaque_bounds_skip_transparent_borders() {
+        let mut image = RgbaImage::from_pixel(8, 6, CLEAR);
+        image.put_pixel(2, 1, Rgba([255, 0, 0, 255]));
+        image.put_pixel(5, 3, Rgba([0, 255, 0, 1]));
+
+        assert_eq!(opaque_bounds(&image), [2, 1, 4, 3]);
+    }
+
+    #[test]
+    fn opaque_bounds_keep_a_pixel_of_clear_images() {
+        assert_eq!(
+            opaque_bounds(&RgbaImage::from_pixel(4, 4, CLEAR)),
+            [0, 0, 1, 1]
+        );
+        assert_eq!(opaque_bounds(&RgbaImage::new(0, 0)), [0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn extrude_repeats_the_edges() {
+        let image = RgbaImage::from_fn(2, 2, |x, y| Rgba([x as u8, y as u8, 0, 255]));
+        let extruded = extrude(&image, 2);
+
+        assert_eq!(extruded.dimensions(), (6, 6));
+        assert_eq!(extruded.get_pixel(0, 0), image.get_pixel(0, 0));
+        assert_eq!(extruded.get_pixel(5, 0), image.get_pixel(1, 0));
+        assert_eq!(extruded.get_pixel(0, 5), image.get_pixel(0, 1));
+        assert_eq!(extruded.get_pixel(5, 5), image.get_pixel(1, 1));
+        assert_eq!(extruded.get_pixel(3, 2), image.get_pixel(1, 0));
+        assert_eq!(extrude(&RgbaImage::new(0, 3), 1).dimensions(), (0, 3));
+    }
+
+    #[test]
+    fn strip_extension_keeps_directories() {
+        assert_eq!(strip_extension("enemies/bat.png"), "enemies/bat");
+        assert_eq!(strip_extension("v1.2/bat"), "v1.2/bat");
+        assert_eq!(strip_extension("bat"), "bat");
+    }
+}
@@ -0,0 +1,229 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use glyph_brush::ab_glyph::FontArc;
+use nalgebra::Vector;
+
+use super::{
+    atlas_textures, pack_atlas, read_packed_atlas, Asset, AssetFile, AssetStore, AtlasSettings,
+};
+use crate::errors::SmolError;
+use crate::gfx::{GfxContext, ImageData};
+use crate::math::Vector2;
+use crate::renderer::shader::Shader;
+use crate::renderer::{Font, Texture};
+use crate::App;
+
+/// Seconds between checks for changed files
+const POLL_INTERVAL: f32 = 0.5;
+
+/// What's rebuilt when the files of a watch change
+#[derive(Debug, Clone)]
+pub(crate) enum WatchedAsset {
+    Texture(Texture),
+    Font(Font),
+    /// Watches the vertex and then the fragment shader
+    Shader(Shader),
+    /// Ids of the pages of a texture atlas, watches every image packed into it
+    Atlas {
+        pages: Vec<u32>,
+        settings: AtlasSettings,
+    },
+    /// Id of an atlas packed ahead of time, watches its image and then its descriptor
+    PackedAtlas(u32),
+}
+
+#[derive(Debug)]
+pub(crate) struct Watch {
+    asset: WatchedAsset,
+    paths: Vec<PathBuf>,
+    modified: Vec<Option<SystemTime>>,
+}
+
+impl Watch {
+    fn new(asset: WatchedAsset, paths: Vec<PathBuf>) -> Self {
+        let modified = paths.iter().map(|path| modified_time(path)).collect();
+
+        Watch {
+            asset,
+            paths,
+            modified,
+        }
+    }
+
+    /// First of the watched files that changed since the last check
+    fn changed(&mut self) -> Option<PathBuf> {
+        let modified: Vec<_> = self.paths.iter().map(|path| modified_time(path)).collect();
+        let index = modified
+            .iter()
+            .zip(&self.modified)
+            .position(|(new, old)| new != old)?;
+        self.modified = modified;
+
+        Some(self.paths[index].clone())
+    }
+}
+
+impl AssetStore {
+    pub(crate) fn unwatch_texture(&mut self, id: u32) {
+        self.watches.retain(|watch| match &watch.asset {
+            WatchedAsset::Texture(texture) => texture.id != id,
+            WatchedAsset::Atlas { pages, .. } => !pages.contains(&id),
+            WatchedAsset::PackedAtlas(atlas) => *atlas != id,
+            _ => true,
+        });
+    }
+
+    /// Updates a frame of a reloaded atlas, names that were unloaded or now belong to another
+    /// texture are left alone. Keeps counting the names using each texture when an image moved
+    /// to another atlas page
+    fn replace_atlas_texture(&mut self, pages: &[u32], name: &str, texture: Texture) {
+        let old = match self.textures.get_mut(name) {
+            Some(stored) if pages.contains(&stored.id) => std::mem::replace(stored, texture),
+            _ => return,
+        };
+        if old.id == texture.id {
+            return;
+        }
+
+        // pages stay allocated for the watch even when no image is left on them
+        if let Some(refs) = self.texture_refs.get_mut(&old.id) {
+            *refs = refs.saturating_sub(1);
+        }
+        *self.texture_refs.entry(texture.id).or_insert(0) += 1;
+    }
+
+    pub(crate) fn unwatch_font(&mut self, font: Font) {
+        self.watches.retain(|watch| match watch.asset {
+            WatchedAsset::Font(watched) => watched != font,
+            _ => true,
+        });
+    }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+impl App {
+    /// Rebuilds every asset loaded from a path whose files changed since it was loaded. GL ids
+    /// are kept, so textures, fonts and shaders already handed out keep working, but a texture
+    /// that changed size needs to be fetched again with `get_texture`. Returns the files that
+    /// couldn't be reloaded, the old asset is kept for them
+    pub fn reload_changed_assets(&mut self) -> Vec<(PathBuf, SmolError)> {
+        let mut errors = Vec::new();
+        for index in 0..self.asset_store.watches.len() {
+            let path = match self.asset_store.watches[index].changed() {
+                Some(path) => path,
+                None => continue,
+            };
+
+            // a file caught halfway through being saved is tried again on its next change
+            if let Err(error) = self.reload_asset(index) {
+                errors.push((path, error));
+            }
+        }
+
+        errors
+    }
+
+    /// Files that failed to reload since the last call, while hot reloading changed files are
+    /// reloaded at the end of every scene
+    pub fn take_reload_errors(&mut self) -> Vec<(PathBuf, SmolError)> {
+        std::mem::take(&mut self.asset_store.reload_errors)
+    }
+
+    pub(crate) fn watch_asset(&mut self, asset: WatchedAsset, paths: Vec<PathBuf>) {
+        if self.asset_store.hot_reload {
+            self.asset_store.watches.push(Watch::new(asset, paths));
+        }
+    }
+
+    /// Checks for changed files every `POLL_INTERVAL` seconds while hot reloading
+    pub(crate) fn poll_asset_changes(&mut self) {
+        if !self.asset_store.hot_reload || self.asset_store.watches.is_empty() {
+            return;
+        }
+
+        self.asset_store.poll_timer += self.delta;
+        if self.asset_store.poll_timer >= POLL_INTERVAL {
+            self.asset_store.poll_timer = 0.;
+            let errors = self.reload_changed_assets();
+            self.asset_store.reload_errors.extend(errors);
+        }
+    }
+
+    fn reload_asset(&mut self, index: usize) -> Result<(), SmolError> {
+        let asset = self.asset_store.watches[index].asset.clone();
+        let paths = self.asset_store.watches[index].paths.clone();
+
+        match asset {
+            WatchedAsset::Texture(texture) => {
+                let file = AssetFile::read(&paths[0])?;
+                let image = ImageData::from_bytes(&file.bytes, &file.extension)?;
+                GfxContext::upload_texture(texture.id, &image);
+
+                let size = Vector::from([image.width as f32, image.height as f32]);
+                for stored in self.asset_store.textures.values_mut() {
+                    if stored.id == texture.id {
+                        *stored = Texture::new(texture.id, size, Vector2::default(), size);
+                    }
+                }
+            }
+            WatchedAsset::Font(font) => {
+                let file = AssetFile::read(&paths[0])?;
+                let font_data = FontArc::try_from_vec(file.bytes.into_owned())?;
+                self.renderer.font_cache.replace_font(font.id, font_data);
+            }
+            WatchedAsset::Shader(shader) => {
+                let vertex = fs::read_to_string(&paths[0])?;
+                let fragment = fs::read_to_string(&paths[1])?;
+                // relinking a program that fails leaves it unusable, so the new sources are
+                // checked first and the old shader keeps drawing until they're fixed
+                let check = GfxContext::build_shader(None, &vertex, &fragment)?;
+                GfxContext::delete_shader(check);
+                GfxContext::build_shader(Some(shader.id), &vertex, &fragment)?;
+            }
+            WatchedAsset::Atlas { pages, settings } => {
+                let files = paths
+                    .iter()
+                    .map(AssetFile::read)
+                    .collect::<Result<Vec<_>, _>>()?;
+                let assets: Vec<Asset> = files.iter().map(AssetFile::as_asset).collect();
+                let packed = pack_atlas(&assets.iter().collect::<Vec<_>>(), &settings)?;
+                if packed.len() != pages.len() {
+                    return Err(SmolError::new(format!(
+                        "Atlas needs {} pages instead of {} after the change, it has to be loaded again",
+                        packed.len(),
+                        pages.len()
+                    )));
+                }
+
+                for (id, page) in pages.iter().zip(&packed) {
+                    GfxContext::upload_texture(*id, &page.image);
+
+                    for (name, texture) in atlas_textures(*id, page) {
+                        self.asset_store
+                            .replace_atlas_texture(&pages, &name, texture);
+                    }
+                }
+            }
+            WatchedAsset::PackedAtlas(id) => {
+                let image = AssetFile::read(&paths[0])?;
+                let descriptor = AssetFile::read(&paths[1])?;
+                let page = read_packed_atlas(&image.as_asset(), &descriptor.as_asset())?;
+                GfxContext::upload_texture(id, &page.image);
+
+                for (name, texture) in atlas_textures(id, &page) {
+                    self.asset_store
+                        .replace_atlas_texture(&[id], &name, texture);
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
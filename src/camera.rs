@@ -1,11 +1,21 @@
-use nalgebra::{Matrix4, Vector, Vector2, Vector3};
+use nalgebra::{Matrix4, Vector, Vector2, Vector3, Vector4};
 
-use crate::glm::{ortho_lh, scale, translate};
+use crate::glm::{ortho_lh, perspective_lh, scale, translate};
+use crate::renderer::shapes::Rectangle;
 
+pub mod controller;
+
+pub use self::controller::*;
+
+#[derive(Debug, Clone, Copy)]
 pub struct Camera {
     pub zoom: f32,
     pub projection_type: Projection,
     pub position: Vector3<f32>,
+    /// Rotation around the z axis in radians
+    pub rotation: f32,
+    pub viewport: Viewport,
+    pub layers: RenderLayers,
 }
 
 impl Default for Camera {
@@ -14,13 +24,37 @@ impl Default for Camera {
             zoom: 1.,
             projection_type: Projection::Orthographic,
             position: Vector3::default(),
+            rotation: 0.,
+            viewport: Viewport::default(),
+            layers: RenderLayers::ALL,
         }
     }
 }
 
+/// Depth range of the orthographic projection, draws with a `Transform::position.z` outside of it
+/// are clipped
+pub const ORTHO_DEPTH: f32 = 1000.;
+
+#[derive(Debug, Clone, Copy)]
 pub enum Projection {
     Orthographic,
-    Perspective,
+    /// The z = 0 plane keeps its pixel scale, draws with a larger z are further away,
+    /// `fov` is the vertical field of view in radians
+    Perspective {
+        fov: f32,
+        near: f32,
+        far: f32,
+    },
+}
+
+impl Projection {
+    pub fn perspective(fov: f32) -> Self {
+        Projection::Perspective {
+            fov,
+            near: 1.,
+            far: 10000.,
+        }
+    }
 }
 
 impl Default for Projection {
@@ -29,32 +63,226 @@ impl Default for Projection {
     }
 }
 
-impl Camera {}
+/// The area of the render target a camera draws into.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Viewport {
+    /// Fractions of the render target, `(0, 0, 1, 1)` covers all of it
+    Normalized(Rectangle),
+    /// Pixels from the top left of the render target
+    Pixels(Rectangle),
+}
+
+impl Default for Viewport {
+    fn default() -> Self {
+        Viewport::Normalized(Rectangle {
+            x: 0.,
+            y: 0.,
+            width: 1.,
+            height: 1.,
+        })
+    }
+}
+
+impl Viewport {
+    pub fn to_pixels(&self, target_size: Vector2<i32>) -> Rectangle {
+        match *self {
+            Viewport::Normalized(rect) => Rectangle {
+                x: rect.x * target_size.x as f32,
+                y: rect.y * target_size.y as f32,
+                width: rect.width * target_size.x as f32,
+                height: rect.height * target_size.y as f32,
+            },
+            Viewport::Pixels(rect) => rect,
+        }
+    }
+}
+
+/// Bit mask of the layers a camera renders, see `Renderer::set_layer`. Layers go from 0 to 31.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RenderLayers(pub u32);
+
+impl RenderLayers {
+    pub const ALL: RenderLayers = RenderLayers(u32::MAX);
+    pub const NONE: RenderLayers = RenderLayers(0);
+    /// Number of layers there are
+    pub const COUNT: u8 = 32;
+
+    /// Panics if `layer` isn't below `RenderLayers::COUNT`
+    pub fn layer(layer: u8) -> Self {
+        Self::NONE.with(layer)
+    }
+
+    /// Panics if `layer` isn't below `RenderLayers::COUNT`
+    pub fn with(self, layer: u8) -> Self {
+        RenderLayers(self.0 | Self::bit(layer))
+    }
+
+    /// Panics if `layer` isn't below `RenderLayers::COUNT`
+    pub fn without(self, layer: u8) -> Self {
+        RenderLayers(self.0 & !Self::bit(layer))
+    }
+
+    /// Layers past `RenderLayers::COUNT` are never contained
+    pub fn contains(&self, layer: u8) -> bool {
+        layer < Self::COUNT && self.0 & 1 << layer != 0
+    }
+
+    fn bit(layer: u8) -> u32 {
+        assert!(
+            layer < Self::COUNT,
+            "Render layer {} is out of range, there are {} layers",
+            layer,
+            Self::COUNT
+        );
+        1 << layer
+    }
+}
+
+impl Default for RenderLayers {
+    fn default() -> Self {
+        Self::ALL
+    }
+}
 
 impl Camera {
     pub fn set_projection(&mut self, projection_type: Projection) {
         self.projection_type = projection_type;
     }
 
-    pub(crate) fn get_projection_matrix(&self, window_size: Vector2<i32>) -> Matrix4<f32> {
+    pub fn set_viewport(&mut self, viewport: Viewport) {
+        self.viewport = viewport;
+    }
+
+    /// The world position in the middle of the viewport
+    pub fn center(&self) -> Vector2<f32> {
+        self.position.xy() / self.zoom
+    }
+
+    /// Moves the camera so `target` is in the middle of the viewport at the current zoom
+    pub fn look_at(&mut self, target: Vector2<f32>) {
+        self.position.x = target.x * self.zoom;
+        self.position.y = target.y * self.zoom;
+    }
+
+    pub fn viewport_size(&self, render_size: Vector2<i32>) -> Vector2<f32> {
+        let viewport = self.viewport.to_pixels(render_size);
+        Vector::from([viewport.width, viewport.height])
+    }
+
+    pub(crate) fn get_projection_matrix(&self, render_size: Vector2<i32>) -> Matrix4<f32> {
+        match self.projection_type {
+            Projection::Orthographic => self.get_screen_projection_matrix(render_size),
+            Projection::Perspective { fov, near, far } => {
+                let size = self.viewport_size(render_size);
+                // distance at which the z = 0 plane covers the viewport pixel for pixel
+                let distance = (size.y / 2.) / (fov / 2.).tan();
+                let proj = perspective_lh(size.x / size.y, fov, near, far);
+                // flip y so it points down like the orthographic projection
+                let flip = Matrix4::new_nonuniform_scaling(&Vector::from([1., -1., 1.]));
+
+                proj * flip * translate(&Matrix4::identity(), &Vector::from([0., 0., distance]))
+            }
+        }
+    }
+
+    /// Orthographic pixel projection of the viewport, used for screen space drawing
+    pub(crate) fn get_screen_projection_matrix(&self, render_size: Vector2<i32>) -> Matrix4<f32> {
+        let size = self.viewport_size(render_size);
         ortho_lh(
-            -(window_size.x as f32 / 2.),
-            window_size.x as f32 / 2.,
-            window_size.y as f32 / 2.,
-            -(window_size.y as f32 / 2.),
-            -1.0,
-            1.0,
+            -(size.x / 2.),
+            size.x / 2.,
+            size.y / 2.,
+            -(size.y / 2.),
+            -ORTHO_DEPTH,
+            ORTHO_DEPTH,
         )
     }
 
-    pub(crate) fn get_projection_view_matrix(&self, window_size: Vector2<i32>) -> Matrix4<f32> {
-        let proj = self.get_projection_matrix(window_size);
+    pub fn get_projection_view_matrix(&self, render_size: Vector2<i32>) -> Matrix4<f32> {
+        let proj = self.get_projection_matrix(render_size);
 
-        let mut view = translate(&Matrix4::identity(), &self.position);
+        let mut view = translate(&Matrix4::identity(), &self.position)
+            * Matrix4::new_rotation(Vector3::z() * self.rotation);
 
         view = view.try_inverse().unwrap();
         view = scale(&view, &Vector::from([self.zoom, self.zoom, 1.]));
 
         proj * view
     }
+
+    /// Maps a position in render pixels, like `Input::get_mouse_pos`, onto the world plane at z = 0
+    pub fn screen_to_world(
+        &self,
+        screen_pos: Vector2<f32>,
+        render_size: Vector2<i32>,
+    ) -> Vector2<f32> {
+        let viewport = self.viewport.to_pixels(render_size);
+        let ndc_x = (screen_pos.x - viewport.x) / viewport.width * 2. - 1.;
+        let ndc_y = 1. - (screen_pos.y - viewport.y) / viewport.height * 2.;
+
+        let inverse = self
+            .get_projection_view_matrix(render_size)
+            .try_inverse()
+            .unwrap_or_else(Matrix4::identity);
+
+        let unproject = |ndc_z: f32| {
+            let point = inverse * Vector4::from([ndc_x, ndc_y, ndc_z, 1.]);
+            point.xyz() / point.w
+        };
+
+        // cast a ray through the near and far planes so this holds for every projection
+        let near = unproject(-1.);
+        let far = unproject(1.);
+        let direction = far - near;
+        let t = if direction.z.abs() > f32::EPSILON {
+            -near.z / direction.z
+        } else {
+            0.
+        };
+
+        (near + direction * t).xy()
+    }
+
+    /// Maps a world position onto render pixels
+    pub fn world_to_screen(
+        &self,
+        world_pos: Vector2<f32>,
+        render_size: Vector2<i32>,
+    ) -> Vector2<f32> {
+        let viewport = self.viewport.to_pixels(render_size);
+        let clip = self.get_projection_view_matrix(render_size)
+            * Vector4::from([world_pos.x, world_pos.y, 0., 1.]);
+        let ndc = clip.xy() / clip.w;
+
+        Vector::from([
+            viewport.x + (ndc.x + 1.) * 0.5 * viewport.width,
+            viewport.y + (1. - ndc.y) * 0.5 * viewport.height,
+        ])
+    }
+
+    /// The world area covered by the viewport, useful for culling
+    pub fn visible_world_rect(&self, render_size: Vector2<i32>) -> Rectangle {
+        let viewport = self.viewport.to_pixels(render_size);
+        let corners = [
+            Vector::from([viewport.x, viewport.y]),
+            Vector::from([viewport.x + viewport.width, viewport.y]),
+            Vector::from([viewport.x, viewport.y + viewport.height]),
+            Vector::from([viewport.x + viewport.width, viewport.y + viewport.height]),
+        ];
+
+        let mut min = Vector::from([f32::MAX, f32::MAX]);
+        let mut max = Vector::from([f32::MIN, f32::MIN]);
+        for corner in corners.iter() {
+            let world = self.screen_to_world(*corner, render_size);
+            min = min.inf(&world);
+            max = max.sup(&world);
+        }
+
+        Rectangle {
+            x: min.x,
+            y: min.y,
+            width: max.x - min.x,
+            height: max.y - min.y,
+        }
+    }
 }
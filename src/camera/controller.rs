@@ -0,0 +1,184 @@
+use nalgebra::{Vector, Vector2};
+
+use crate::renderer::shapes::Rectangle;
+
+use super::Camera;
+
+#[derive(Debug, Clone, Copy)]
+pub enum Smoothing {
+    /// Snaps straight to the target
+    None,
+    /// Closes the distance to the target at the given rate per second
+    Lerp(f32),
+    Spring {
+        stiffness: f32,
+        damping: f32,
+    },
+}
+
+impl Default for Smoothing {
+    fn default() -> Self {
+        Smoothing::Lerp(8.)
+    }
+}
+
+/// Drives a `Camera` towards a target, call `update` once per frame with `App::delta`.
+#[derive(Debug, Clone, Copy)]
+pub struct CameraController {
+    pub target: Vector2<f32>,
+    pub smoothing: Smoothing,
+    /// Area around the middle of the view, in world units, the target can move in without
+    /// moving the camera
+    pub deadzone: Option<Rectangle>,
+    /// World area the view is kept inside of
+    pub bounds: Option<Rectangle>,
+    pub target_zoom: f32,
+    pub min_zoom: f32,
+    pub max_zoom: f32,
+    /// Rate per second the zoom closes in on `target_zoom`
+    pub zoom_speed: f32,
+    /// Rotation of the camera before shake is applied
+    pub rotation: f32,
+    /// Shake strength from 0 to 1, see `add_trauma`
+    pub trauma: f32,
+    /// Trauma removed per second
+    pub trauma_decay: f32,
+    pub max_shake_offset: f32,
+    /// Maximum shake rotation in radians
+    pub max_shake_angle: f32,
+    center: Vector2<f32>,
+    velocity: Vector2<f32>,
+    zoom: f32,
+}
+
+impl Default for CameraController {
+    fn default() -> Self {
+        Self::new(&Camera::default())
+    }
+}
+
+impl CameraController {
+    pub fn new(camera: &Camera) -> Self {
+        let center = camera.center();
+
+        Self {
+            target: center,
+            smoothing: Smoothing::default(),
+            deadzone: None,
+            bounds: None,
+            target_zoom: camera.zoom,
+            min_zoom: 0.1,
+            max_zoom: 10.,
+            zoom_speed: 8.,
+            rotation: camera.rotation,
+            trauma: 0.,
+            trauma_decay: 1.,
+            max_shake_offset: 16.,
+            max_shake_angle: 0.1,
+            center,
+            velocity: Vector2::default(),
+            zoom: camera.zoom,
+        }
+    }
+
+    pub fn follow(&mut self, target: Vector2<f32>) {
+        self.target = target;
+    }
+
+    /// Moves straight to the target and zoom, skipping any smoothing
+    pub fn snap_to_target(&mut self) {
+        self.center = self.target;
+        self.velocity = Vector2::default();
+        self.zoom = self.clamped_target_zoom();
+    }
+
+    /// `target_zoom` kept between the zoom limits, whichever order they're set in
+    fn clamped_target_zoom(&self) -> f32 {
+        let min = self.min_zoom.min(self.max_zoom);
+        let max = self.min_zoom.max(self.max_zoom);
+        self.target_zoom.max(min).min(max)
+    }
+
+    pub fn add_trauma(&mut self, amount: f32) {
+        self.trauma = (self.trauma + amount).clamp(0., 1.);
+    }
+
+    pub fn update(&mut self, camera: &mut Camera, render_size: Vector2<i32>, delta: f32) {
+        let target_zoom = self.clamped_target_zoom();
+        self.zoom += (target_zoom - self.zoom) * approach(self.zoom_speed, delta);
+
+        let desired = self.deadzone_target();
+
+        match self.smoothing {
+            Smoothing::None => self.center = desired,
+            Smoothing::Lerp(speed) => {
+                self.center += (desired - self.center) * approach(speed, delta);
+            }
+            Smoothing::Spring { stiffness, damping } => {
+                let acceleration = (desired - self.center) * stiffness - self.velocity * damping;
+                self.velocity += acceleration * delta;
+                self.center += self.velocity * delta;
+            }
+        }
+
+        let half_view = camera.viewport_size(render_size) / (2. * self.zoom);
+        if let Some(bounds) = self.bounds {
+            self.center = Vector::from([
+                clamp_axis(self.center.x, bounds.x, bounds.width, half_view.x),
+                clamp_axis(self.center.y, bounds.y, bounds.height, half_view.y),
+            ]);
+        }
+
+        self.trauma = f32::max(0., self.trauma - self.trauma_decay * delta);
+        let shake = self.trauma * self.trauma;
+        let offset =
+            Vector::from([random_signed(), random_signed()]) * self.max_shake_offset * shake;
+
+        camera.zoom = self.zoom;
+        camera.rotation = self.rotation + random_signed() * self.max_shake_angle * shake;
+        camera.look_at(self.center + offset / self.zoom);
+    }
+
+    fn deadzone_target(&self) -> Vector2<f32> {
+        let deadzone = if let Some(deadzone) = self.deadzone {
+            deadzone
+        } else {
+            return self.target;
+        };
+
+        let mut desired = self.center;
+        let left = self.center.x + deadzone.x;
+        let top = self.center.y + deadzone.y;
+
+        if self.target.x < left {
+            desired.x -= left - self.target.x;
+        } else if self.target.x > left + deadzone.width {
+            desired.x += self.target.x - (left + deadzone.width);
+        }
+
+        if self.target.y < top {
+            desired.y -= top - self.target.y;
+        } else if self.target.y > top + deadzone.height {
+            desired.y += self.target.y - (top + deadzone.height);
+        }
+
+        desired
+    }
+}
+
+/// Fraction of the remaining distance to cover this frame, independent of frame rate
+fn approach(rate: f32, delta: f32) -> f32 {
+    1. - (-rate * delta).exp()
+}
+
+fn clamp_axis(center: f32, min: f32, size: f32, half_view: f32) -> f32 {
+    if size <= half_view * 2. {
+        min + size / 2.
+    } else {
+        center.clamp(min + half_view, min + size - half_view)
+    }
+}
+
+fn random_signed() -> f32 {
+    rand::random::<f32>() * 2. - 1.
+}
@@ -1,3 +1,5 @@
+use crate::errors::SmolError;
+
 #[derive(Debug, Clone, Copy, Default, PartialEq)]
 pub struct Color(pub u8, pub u8, pub u8, pub f32);
 
@@ -21,3 +23,35 @@ impl Color {
     pub const GREEN: Color = Color(10, 255, 10, 1.);
     pub const BLACK: Color = Color(1, 1, 1, 1.);
 }
+
+impl std::str::FromStr for Color {
+    type Err = SmolError;
+
+    /// Parses a colour name like `"red"` or hex like `"#ff8800"` and `"#ff880080"`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "white" => return Ok(Color::WHITE),
+            "blue" => return Ok(Color::BLUE),
+            "red" => return Ok(Color::RED),
+            "green" => return Ok(Color::GREEN),
+            "black" => return Ok(Color::BLACK),
+            _ => {}
+        }
+
+        let invalid = || SmolError::new(format!("Invalid color {}", s));
+        let hex = s.strip_prefix('#').unwrap_or(s);
+        if (hex.len() != 6 && hex.len() != 8) || !hex.is_ascii() {
+            return Err(invalid());
+        }
+
+        let channel =
+            |index: usize| u8::from_str_radix(&hex[index..index + 2], 16).map_err(|_| invalid());
+        let alpha = if hex.len() == 8 {
+            channel(6)? as f32 / 255.
+        } else {
+            1.
+        };
+
+        Ok(Color(channel(0)?, channel(2)?, channel(4)?, alpha))
+    }
+}
@@ -34,6 +34,14 @@ impl std::convert::From<ron::de::Error> for SmolError {
     }
 }
 
+impl std::convert::From<serde_json::Error> for SmolError {
+    fn from(e: serde_json::Error) -> SmolError {
+        SmolError {
+            message: e.to_string(),
+        }
+    }
+}
+
 impl std::convert::From<std::ffi::NulError> for SmolError {
     fn from(e: std::ffi::NulError) -> SmolError {
         SmolError {
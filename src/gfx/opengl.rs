@@ -1,3 +1,4 @@
+use std::cell::RefCell;
 use std::ffi::c_void;
 use std::ffi::CString;
 use std::mem;
@@ -9,6 +10,7 @@ use gl::types::GLchar;
 use gl::types::GLint;
 use gl::types::GLsizeiptr;
 use gl::types::GLuint;
+use hashbrown::HashMap;
 use image::DynamicImage;
 use nalgebra::Vector2;
 use sdl2::video::GLContext;
@@ -20,6 +22,7 @@ use sdl2::Sdl;
 use crate::color::Color;
 use crate::errors::SmolError;
 use crate::renderer::core::Vertex;
+use crate::renderer::shapes::Rectangle;
 use crate::renderer::Texture;
 use crate::renderer::MAX_BATCH_SIZE;
 use crate::AppSettings;
@@ -52,23 +55,35 @@ impl ImageData {
         }
     }
 
-    fn generate_aseprite_image<'a>(bytes: &'a [u8]) -> Self {
+    pub fn from_rgba(width: u32, height: u32, data: Vec<u8>) -> Self {
+        Self::new(width, height, data, gl::RGBA8, gl::RGBA)
+    }
+
+    /// Decodes an image file, aseprite files use their first frame
+    pub fn from_bytes(bytes: &[u8], extension: &str) -> Result<Self, SmolError> {
+        match extension {
+            "aseprite" => Self::generate_aseprite_image(bytes),
+            _ => Self::generate_standard_image(bytes),
+        }
+    }
+
+    fn generate_aseprite_image<'a>(bytes: &'a [u8]) -> Result<Self, SmolError> {
         use asefile::AsepriteFile;
 
-        let file = AsepriteFile::read(bytes).expect("Could not load aseprite file from bytes");
+        let file = AsepriteFile::read(bytes).map_err(|e| SmolError::new(e.to_string()))?;
         let image = file.frame(0).image();
 
-        Self {
+        Ok(Self {
             width: image.width(),
             height: image.height(),
             data: image.into_raw(),
             internal_format: gl::RGBA8,
             format: gl::RGBA,
-        }
+        })
     }
 
-    fn generate_standard_image<'a>(bytes: &'a [u8]) -> Self {
-        match image::load_from_memory(bytes).expect("Could not load image at src: {}") {
+    fn generate_standard_image<'a>(bytes: &'a [u8]) -> Result<Self, SmolError> {
+        let image_data = match image::load_from_memory(bytes)? {
             DynamicImage::ImageRgba8(_image) => ImageData::new(
                 _image.width(),
                 _image.height(),
@@ -93,15 +108,40 @@ impl ImageData {
                     gl::RGBA,
                 )
             }
-        }
+        };
+
+        Ok(image_data)
     }
 }
 
 type TextureId = u32;
 
+thread_local! {
+    /// Bumped whenever a GL texture id is created or deleted, GL reuses deleted ids so copies of
+    /// a `Texture` tell whether the id still holds their texture by its generation
+    static TEXTURE_GENERATIONS: RefCell<HashMap<TextureId, u32>> = RefCell::new(HashMap::new());
+}
+
+/// Effect settings of the signed distance field text shader, distances are in pixels of the
+/// text before any camera zoom
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub(crate) struct SdfUniforms {
+    /// Pixels of text per unit of the distance texture
+    pub distance_scale: f32,
+    pub outline_color: [f32; 4],
+    pub outline_width: f32,
+    pub glow_color: [f32; 4],
+    pub glow_radius: f32,
+    pub shadow_color: [f32; 4],
+    /// Offset of the shadow in texture coordinates
+    pub shadow_offset: [f32; 2],
+    pub shadow_softness: f32,
+}
+
 #[derive(Default)]
 pub(crate) struct GfxContext {
     default_shader: u32,
+    sdf_shader: u32,
     vao_id: u32,
     vertex_buffer_id: u32,
     index_buffer_id: u32,
@@ -116,6 +156,11 @@ impl GfxContext {
         let fs = compile_shader(include_str!("../shaders/opengl/2d.fs"), gl::FRAGMENT_SHADER);
         let vs = compile_shader(include_str!("../shaders/opengl/2d.vs"), gl::VERTEX_SHADER);
         let default_shader = link_program(vs, fs);
+        let sdf_fs = compile_shader(
+            include_str!("../shaders/opengl/sdf.fs"),
+            gl::FRAGMENT_SHADER,
+        );
+        let sdf_shader = link_program(vs, sdf_fs);
 
         let mut vao_id = 0;
         let mut vertex_buffer_id: u32 = 0;
@@ -159,16 +204,20 @@ impl GfxContext {
             .unwrap();
 
             gl::Viewport(0, 0, render_size.x, render_size.y);
+            gl::Enable(gl::SCISSOR_TEST);
+            gl::Scissor(0, 0, render_size.x, render_size.y);
             gl::BindBuffer(gl::ARRAY_BUFFER, 0);
             gl::BindVertexArray(0);
             gl::Enable(gl::BLEND);
             gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
             gl::DeleteShader(fs);
+            gl::DeleteShader(sdf_fs);
             gl::DeleteShader(vs);
         }
 
         GfxContext {
             default_shader,
+            sdf_shader,
             vao_id,
             vertex_buffer_id,
             index_buffer_id,
@@ -212,6 +261,7 @@ impl GfxContext {
 
             gl::BindTexture(gl::TEXTURE_2D, 0);
         };
+        Self::bump_texture_generation(texture_id);
 
         texture_id
     }
@@ -246,20 +296,69 @@ impl GfxContext {
                 ptr::null(),
             );
             gl_assert_ok!();
+        }
+        Self::bump_texture_generation(name);
 
-            name
+        name
+    }
+
+    pub fn delete_texture(texture_id: TextureId) {
+        unsafe {
+            gl::DeleteTextures(1, &texture_id);
         }
+        Self::bump_texture_generation(texture_id);
     }
 
-    pub fn generate_texture<'a>(bytes: &'a [u8], extention: &str) -> (i32, i32, TextureId) {
-        let mut texture_id = 0;
+    /// Generation of the texture a GL id holds, see `Texture::is_alive`
+    pub(crate) fn texture_generation(texture_id: TextureId) -> u32 {
+        TEXTURE_GENERATIONS.with(|generations| {
+            generations
+                .borrow()
+                .get(&texture_id)
+                .copied()
+                .unwrap_or_default()
+        })
+    }
 
-        let image_data = match extention {
-            "aseprite" => ImageData::generate_aseprite_image(bytes),
-            _ => ImageData::generate_standard_image(bytes),
-        };
+    fn bump_texture_generation(texture_id: TextureId) {
+        TEXTURE_GENERATIONS.with(|generations| {
+            let mut generations = generations.borrow_mut();
+            let generation = generations.entry(texture_id).or_insert(0);
+            *generation = generation.wrapping_add(1);
+        });
+    }
 
-        let img_ptr = image_data.data.as_ptr() as *const c_void;
+    /// Uploads single channel pixels into part of a texture made by `generate_font_texture`
+    pub fn update_font_texture(
+        texture_id: TextureId,
+        (x, y): (u32, u32),
+        (width, height): (u32, u32),
+        data: &[u8],
+    ) {
+        unsafe {
+            gl::BindTexture(gl::TEXTURE_2D, texture_id);
+            gl::TexSubImage2D(
+                gl::TEXTURE_2D,
+                0,
+                x as _,
+                y as _,
+                width as _,
+                height as _,
+                gl::RED,
+                gl::UNSIGNED_BYTE,
+                data.as_ptr() as _,
+            );
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+        }
+    }
+
+    pub fn generate_texture<'a>(
+        bytes: &'a [u8],
+        extention: &str,
+    ) -> Result<(i32, i32, TextureId), SmolError> {
+        let mut texture_id = 0;
+
+        let image_data = ImageData::from_bytes(bytes, extention)?;
 
         unsafe {
             gl::GenTextures(1, &mut texture_id);
@@ -270,7 +369,27 @@ impl GfxContext {
             // set texture filtering parameters
             gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::NEAREST as i32);
             gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::NEAREST as i32);
+        };
+        Self::bump_texture_generation(texture_id);
+        Self::upload_texture(texture_id, &image_data);
+
+        Ok((image_data.width as _, image_data.height as _, texture_id))
+    }
 
+    /// Largest width and height a texture can have, 0 when it can't be queried
+    pub fn max_texture_size() -> u32 {
+        let mut size = 0;
+        unsafe {
+            gl::GetIntegerv(gl::MAX_TEXTURE_SIZE, &mut size);
+        }
+
+        size.max(0) as u32
+    }
+
+    /// Replaces the pixels of a texture, its id stays the same so handles to it stay valid
+    pub fn upload_texture(texture_id: TextureId, image_data: &ImageData) {
+        unsafe {
+            gl::BindTexture(gl::TEXTURE_2D, texture_id);
             gl::TexImage2D(
                 gl::TEXTURE_2D,
                 0,
@@ -280,14 +399,73 @@ impl GfxContext {
                 0,
                 image_data.format,
                 gl::UNSIGNED_BYTE,
-                img_ptr,
+                image_data.data.as_ptr() as *const c_void,
             );
             gl::GenerateMipmap(gl::TEXTURE_2D);
 
             gl::BindTexture(gl::TEXTURE_2D, 0);
+        }
+    }
+
+    /// Compiles and links a shader program from GLSL sources, relinking `program` in place when
+    /// it's given so its id stays the same
+    pub fn build_shader(
+        program: Option<u32>,
+        vertex: &str,
+        fragment: &str,
+    ) -> Result<u32, SmolError> {
+        let vs = try_compile_shader(vertex, gl::VERTEX_SHADER)?;
+        let fs = match try_compile_shader(fragment, gl::FRAGMENT_SHADER) {
+            Ok(fs) => fs,
+            Err(error) => {
+                unsafe { gl::DeleteShader(vs) };
+                return Err(error);
+            }
         };
 
-        (image_data.width as _, image_data.height as _, texture_id)
+        unsafe {
+            let program = program.unwrap_or_else(|| gl::CreateProgram());
+            let mut attached = [0; 2];
+            let mut count = 0;
+            gl::GetAttachedShaders(program, 2, &mut count, attached.as_mut_ptr());
+            for shader in &attached[..count as usize] {
+                gl::DetachShader(program, *shader);
+            }
+
+            gl::AttachShader(program, vs);
+            gl::AttachShader(program, fs);
+            gl::LinkProgram(program);
+            gl::DetachShader(program, vs);
+            gl::DetachShader(program, fs);
+            gl::DeleteShader(vs);
+            gl::DeleteShader(fs);
+
+            let mut status = GLint::from(gl::FALSE);
+            gl::GetProgramiv(program, gl::LINK_STATUS, &mut status);
+            if status != GLint::from(gl::TRUE) {
+                let mut len = 0;
+                gl::GetProgramiv(program, gl::INFO_LOG_LENGTH, &mut len);
+                let mut buf = vec![0u8; len.max(1) as usize];
+                gl::GetProgramInfoLog(
+                    program,
+                    len,
+                    ptr::null_mut(),
+                    buf.as_mut_ptr() as *mut GLchar,
+                );
+                return Err(SmolError::new(format!(
+                    "Couldn't link shader: {}",
+                    String::from_utf8_lossy(&buf).trim_end_matches('\0')
+                )));
+            }
+
+            Ok(program)
+        }
+    }
+
+    pub fn delete_shader(program: u32) {
+        unsafe {
+            gl::DeleteProgram(program);
+        }
     }
 
     pub(crate) fn render(
@@ -296,16 +474,24 @@ impl GfxContext {
         indicies: &Vec<i32>,
         bound_texture_map: &Vec<Texture>,
         projection_view_matrix: &crate::glm::Mat4x4,
+        sdf: Option<&SdfUniforms>,
+        custom_shader: Option<u32>,
     ) {
+        let shader = match (sdf, custom_shader) {
+            (Some(_), _) => self.sdf_shader,
+            (None, Some(custom_shader)) => custom_shader,
+            (None, None) => self.default_shader,
+        };
+
         unsafe {
-            gl::UseProgram(self.default_shader);
+            gl::UseProgram(shader);
 
             for (index, texture) in bound_texture_map.iter().enumerate() {
                 gl::ActiveTexture(gl::TEXTURE0 + index as gl::types::GLenum);
                 self.bind_texture(&texture);
             }
 
-            let loc = get_uniform_location(self.default_shader, "u_textures");
+            let loc = get_uniform_location(shader, "u_textures");
             let texture_index_arr: Vec<u32> =
                 (0..bound_texture_map.len()).map(|v| v as u32).collect();
 
@@ -315,7 +501,7 @@ impl GfxContext {
                 texture_index_arr.as_ptr() as _,
             );
 
-            let projection_location = get_uniform_location(self.default_shader, "projection_view");
+            let projection_location = get_uniform_location(shader, "projection_view");
 
             gl::UniformMatrix4fv(
                 projection_location,
@@ -324,6 +510,42 @@ impl GfxContext {
                 projection_view_matrix.as_ptr(),
             );
 
+            if let Some(sdf) = sdf {
+                gl::Uniform1f(
+                    get_uniform_location(shader, "distance_scale"),
+                    sdf.distance_scale,
+                );
+                gl::Uniform4fv(
+                    get_uniform_location(shader, "outline_color"),
+                    1,
+                    sdf.outline_color.as_ptr(),
+                );
+                gl::Uniform1f(
+                    get_uniform_location(shader, "outline_width"),
+                    sdf.outline_width,
+                );
+                gl::Uniform4fv(
+                    get_uniform_location(shader, "glow_color"),
+                    1,
+                    sdf.glow_color.as_ptr(),
+                );
+                gl::Uniform1f(get_uniform_location(shader, "glow_radius"), sdf.glow_radius);
+                gl::Uniform4fv(
+                    get_uniform_location(shader, "shadow_color"),
+                    1,
+                    sdf.shadow_color.as_ptr(),
+                );
+                gl::Uniform2fv(
+                    get_uniform_location(shader, "shadow_offset"),
+                    1,
+                    sdf.shadow_offset.as_ptr(),
+                );
+                gl::Uniform1f(
+                    get_uniform_location(shader, "shadow_softness"),
+                    sdf.shadow_softness,
+                );
+            }
+
             gl::BindVertexArray(self.vao_id);
             gl::BindBuffer(gl::ARRAY_BUFFER, self.vertex_buffer_id);
             gl::BufferSubData(
@@ -353,14 +575,76 @@ impl GfxContext {
         }
     }
 
+    /// Binds `target` for drawing, `None` draws straight to the window
+    pub fn bind_render_target(&self, target: Option<&RenderTarget>) {
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, target.map_or(0, |t| t.framebuffer));
+        }
+    }
+
     pub fn swap_buffer(&self, window: &Window) {
         window.gl_swap_window();
     }
 
-    pub fn resize_window(&self, width: i32, height: i32) {
+    /// Sets the viewport and scissor from a rectangle measured from the top left of the target,
+    /// so clearing only touches the viewport
+    pub fn set_viewport(&self, viewport: Rectangle, target_size: Vector2<i32>) {
+        let x = viewport.x as i32;
+        let y = target_size.y - (viewport.y + viewport.height) as i32;
+        let width = viewport.width as i32;
+        let height = viewport.height as i32;
+        unsafe {
+            gl::Viewport(x, y, width, height);
+            gl::Scissor(x, y, width, height);
+        }
+    }
+}
+
+/// Offscreen framebuffer the renderer draws into when a virtual resolution is set
+#[derive(Debug)]
+pub(crate) struct RenderTarget {
+    framebuffer: u32,
+    pub(crate) texture: Texture,
+    pub(crate) size: Vector2<i32>,
+}
+
+impl RenderTarget {
+    pub fn new(size: Vector2<i32>) -> Self {
+        let texture_id = GfxContext::generate_empty_texture(size.x, size.y, ptr::null());
+        let mut framebuffer = 0;
+        unsafe {
+            gl::GenFramebuffers(1, &mut framebuffer);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, framebuffer);
+            gl::FramebufferTexture2D(
+                gl::FRAMEBUFFER,
+                gl::COLOR_ATTACHMENT0,
+                gl::TEXTURE_2D,
+                texture_id,
+                0,
+            );
+            debug_assert_eq!(
+                gl::CheckFramebufferStatus(gl::FRAMEBUFFER),
+                gl::FRAMEBUFFER_COMPLETE
+            );
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+        }
+
+        let texture_size = nalgebra::Vector::from([size.x as f32, size.y as f32]);
+
+        Self {
+            framebuffer,
+            texture: Texture::new(texture_id, texture_size, Vector2::default(), texture_size),
+            size,
+        }
+    }
+}
+
+impl Drop for RenderTarget {
+    fn drop(&mut self) {
         unsafe {
-            gl::Viewport(0, 0, width, height);
+            gl::DeleteFramebuffers(1, &self.framebuffer);
         }
+        GfxContext::delete_texture(self.texture.id);
     }
 }
 
@@ -368,6 +652,7 @@ impl Drop for GfxContext {
     fn drop(&mut self) {
         unsafe {
             gl::DeleteProgram(self.default_shader);
+            gl::DeleteProgram(self.sdf_shader);
         }
     }
 }
@@ -418,6 +703,39 @@ pub fn compile_shader(src: &str, ty: gl::types::GLenum) -> u32 {
     shader
 }
 
+/// Like `compile_shader` but returns the error log instead of panicking, for shaders that can be
+/// edited while the game runs
+fn try_compile_shader(src: &str, ty: gl::types::GLenum) -> Result<u32, SmolError> {
+    let c_str = CString::new(src.as_bytes())?;
+    unsafe {
+        let shader = gl::CreateShader(ty);
+        gl::ShaderSource(shader, 1, &c_str.as_ptr(), ptr::null());
+        gl::CompileShader(shader);
+
+        let mut status = GLint::from(gl::FALSE);
+        gl::GetShaderiv(shader, gl::COMPILE_STATUS, &mut status);
+        if status != GLint::from(gl::TRUE) {
+            let mut len = 0;
+            gl::GetShaderiv(shader, gl::INFO_LOG_LENGTH, &mut len);
+            let mut buf = vec![0u8; len.max(1) as usize];
+            gl::GetShaderInfoLog(
+                shader,
+                len,
+                ptr::null_mut(),
+                buf.as_mut_ptr() as *mut GLchar,
+            );
+            gl::DeleteShader(shader);
+
+            return Err(SmolError::new(format!(
+                "Couldn't compile shader: {}",
+                String::from_utf8_lossy(&buf).trim_end_matches('\0')
+            )));
+        }
+
+        Ok(shader)
+    }
+}
+
 pub fn link_program(vs: u32, fs: u32) -> u32 {
     unsafe {
         let program = gl::CreateProgram();
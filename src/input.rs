@@ -1,5 +1,6 @@
 use crate::math::Vector2;
 use crate::MouseButton;
+use crate::Rectangle;
 use nalgebra::Vector;
 use sdl2::keyboard::Keycode;
 use sdl2::mouse::MouseState;
@@ -13,6 +14,8 @@ pub struct Input {
     pub(crate) current_mouse_state: HashSet<MouseButton>,
     pub mouse_scroll_direction: i32,
     mouse_state: MouseState,
+    screen_rect: Rectangle,
+    render_size: Vector2<i32>,
 }
 
 impl Input {
@@ -24,6 +27,8 @@ impl Input {
             current_mouse_state: HashSet::new(),
             mouse_scroll_direction: 0,
             mouse_state: MouseState::from_sdl_state(0),
+            screen_rect: Rectangle::default(),
+            render_size: Vector2::default(),
         }
     }
 }
@@ -82,7 +87,29 @@ impl Input {
         self.input_current_keyboard_state = keys;
     }
 
+    /// Mouse position in render pixels, which differ from window pixels with a virtual resolution
+    /// or resize policy
     pub fn get_mouse_pos(&mut self) -> Vector2<i32> {
+        if self.screen_rect.width <= 0. || self.screen_rect.height <= 0. {
+            return self.get_window_mouse_pos();
+        }
+
+        Vector::from([
+            ((self.mouse_state.x() as f32 - self.screen_rect.x) / self.screen_rect.width
+                * self.render_size.x as f32)
+                .floor() as i32,
+            ((self.mouse_state.y() as f32 - self.screen_rect.y) / self.screen_rect.height
+                * self.render_size.y as f32)
+                .floor() as i32,
+        ])
+    }
+
+    pub fn get_window_mouse_pos(&self) -> Vector2<i32> {
         Vector::from([self.mouse_state.x(), self.mouse_state.y()])
     }
+
+    pub(crate) fn set_screen_mapping(&mut self, screen_rect: Rectangle, render_size: Vector2<i32>) {
+        self.screen_rect = screen_rect;
+        self.render_size = render_size;
+    }
 }
@@ -1,10 +1,12 @@
+pub mod animation;
 pub mod asset_store;
-mod camera;
+pub mod camera;
 pub mod collision;
 pub mod color;
 pub mod errors;
 pub mod gfx;
 pub mod input;
+pub mod localization;
 pub mod renderer;
 pub mod transform;
 pub mod window;
@@ -19,7 +21,9 @@ pub use crate::color::*;
 use crate::gfx::build_window;
 use crate::input::Input;
 pub use crate::renderer::shapes::*;
+use crate::renderer::text::{DEFAULT_GLYPH_CACHE_SIZE, DEFAULT_MAX_GLYPH_CACHE_SIZE};
 use crate::renderer::Renderer;
+use crate::renderer::{ResizePolicy, ScalingMode};
 pub use crate::transform::*;
 use math::Vector;
 use math::Vector2;
@@ -28,21 +32,45 @@ use sdl2::event::WindowEvent;
 use sdl2::video::Window;
 use sdl2::EventPump;
 use spin_sleep::LoopHelper;
+use std::path::PathBuf;
 
 pub use nalgebra_glm as glm;
 
 pub use nalgebra as math;
 
+#[deprecated(note = "use `AppSettings::virtual_resolution` instead")]
 pub const RENDER_SCALE: f32 = 2.;
 
+#[deprecated(note = "use `AppSettings::virtual_resolution` instead")]
 pub const RENDER_RES_W: i32 = 640;
+#[deprecated(note = "use `AppSettings::virtual_resolution` instead")]
 pub const RENDER_RES_H: i32 = 360;
+#[deprecated(note = "use `AppSettings::virtual_resolution` instead")]
+#[allow(deprecated)]
 pub const BASE_RES_W: f32 = RENDER_RES_W as f32 * RENDER_SCALE;
+#[deprecated(note = "use `AppSettings::virtual_resolution` instead")]
+#[allow(deprecated)]
 pub const BASE_RES_H: f32 = RENDER_RES_H as f32 * RENDER_SCALE;
 
 pub struct AppSettings {
     pub size: Vector2<i32>,
     pub target_fps: f32,
+    /// Resolution everything is drawn at before being scaled into the window,
+    /// `None` draws at the window size
+    pub virtual_resolution: Option<Vector2<i32>>,
+    pub scaling: ScalingMode,
+    pub resize_policy: ResizePolicy,
+    /// Starting size of the texture glyphs are cached in, it doubles when a frame's text
+    /// doesn't fit
+    pub glyph_cache_size: Vector2<u32>,
+    /// Size the glyph cache stops growing at, text that still doesn't fit is drawn a piece at
+    /// a time
+    pub max_glyph_cache_size: Vector2<u32>,
+    /// Directory assets are read from at runtime, relative to the working directory. `asset!`
+    /// embeds from the `assets` directory next to `Cargo.toml`, so it should hold the same files
+    pub asset_root: PathBuf,
+    /// Reloads assets loaded from paths when their files change, on by default in debug builds
+    pub hot_reload: bool,
 }
 
 impl Default for AppSettings {
@@ -50,6 +78,16 @@ impl Default for AppSettings {
         Self {
             size: Vector::from([1280, 720]),
             target_fps: 60.,
+            virtual_resolution: None,
+            scaling: ScalingMode::default(),
+            resize_policy: ResizePolicy::default(),
+            glyph_cache_size: Vector::from([DEFAULT_GLYPH_CACHE_SIZE, DEFAULT_GLYPH_CACHE_SIZE]),
+            max_glyph_cache_size: Vector::from([
+                DEFAULT_MAX_GLYPH_CACHE_SIZE,
+                DEFAULT_MAX_GLYPH_CACHE_SIZE,
+            ]),
+            asset_root: PathBuf::from("assets"),
+            hot_reload: cfg!(debug_assertions) && !cfg!(feature = "embed-assets"),
         }
     }
 }
@@ -57,7 +95,6 @@ impl Default for AppSettings {
 pub struct App {
     event_pump: EventPump,
     loop_helper: LoopHelper,
-    window: Window,
     running: bool,
     pub renderer: Renderer,
     pub asset_store: AssetStore,
@@ -65,6 +102,10 @@ pub struct App {
     pub delta: f32,
     pub frame_rate: f32,
     pub window_size: Vector2<i32>,
+    /// The new window size when the window was resized during the last frame
+    pub resized: Option<Vector2<i32>>,
+    // dropped after the renderer and asset store so they can still free their GL resources
+    window: Window,
     #[cfg(feature = "opengl")]
     _gl_context: sdl2::video::GLContext,
 }
@@ -74,9 +115,12 @@ impl App {
         let sdl_context = sdl2::init().unwrap();
         let (window, _gl_context) = build_window(&sdl_context, &settings);
         let window_size = settings.size.clone();
-        let renderer = Renderer::new(settings.size);
+        let renderer = Renderer::new(&settings);
         let event_pump = sdl_context.event_pump().unwrap();
 
+        let mut input = Input::new();
+        input.set_screen_mapping(renderer.present_rect, renderer.render_size());
+
         let loop_helper = LoopHelper::builder()
             .report_interval_s(0.5) // report every half a second
             .build_with_target_rate(settings.target_fps); // limit to 250 FPS if possible
@@ -87,12 +131,13 @@ impl App {
             loop_helper,
             window,
             renderer,
-            asset_store: AssetStore::default(),
+            asset_store: AssetStore::new(settings.asset_root.clone(), settings.hot_reload),
             delta: 1. / 60.,
             frame_rate: 60.,
             _gl_context,
             window_size,
-            input: Input::new(),
+            resized: None,
+            input,
         }
     }
 
@@ -109,7 +154,9 @@ impl App {
     pub fn end_scene(&mut self) {
         self.renderer.render(); // render batch
         self.renderer.swap_buffer(&self.window);
+        self.poll_asset_changes();
         let mut mouse_scroll_direction = 0;
+        self.resized = None;
         for event in self.event_pump.poll_iter() {
             match event {
                 Event::Quit { .. }
@@ -121,8 +168,13 @@ impl App {
                 }
                 Event::Window { win_event, .. } => match win_event {
                     WindowEvent::Resized(width, height) => {
-                        self.renderer.context.resize_window(width, height);
                         self.window_size = Vector::from([width, height]);
+                        self.resized = Some(self.window_size);
+                        self.renderer.set_window_size(self.window_size);
+                        self.input.set_screen_mapping(
+                            self.renderer.present_rect,
+                            self.renderer.render_size(),
+                        );
                     }
                     _ => {}
                 },
@@ -0,0 +1,370 @@
+use std::collections::HashMap as SerdeMap;
+use std::fmt::Write;
+
+use hashbrown::HashMap;
+use serde::Deserialize;
+
+use crate::asset_store::Asset;
+use crate::errors::SmolError;
+use crate::renderer::text::TextSettings;
+use crate::renderer::{Font, Renderer};
+use crate::App;
+
+/// Value filling a `{name}` placeholder of a localized string
+#[derive(Debug, Clone, PartialEq)]
+pub enum LocaleArg {
+    Text(String),
+    /// Also picks the plural form when it's named `count`
+    Number(f64),
+}
+
+impl std::fmt::Display for LocaleArg {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LocaleArg::Text(text) => f.write_str(text),
+            LocaleArg::Number(number) => write!(f, "{}", number),
+        }
+    }
+}
+
+impl From<&str> for LocaleArg {
+    fn from(text: &str) -> Self {
+        LocaleArg::Text(text.to_owned())
+    }
+}
+
+impl From<String> for LocaleArg {
+    fn from(text: String) -> Self {
+        LocaleArg::Text(text)
+    }
+}
+
+macro_rules! number_locale_arg {
+    ($($number:ty),*) => {
+        $(impl From<$number> for LocaleArg {
+            fn from(number: $number) -> Self {
+                LocaleArg::Number(number as f64)
+            }
+        })*
+    };
+}
+
+number_locale_arg!(i32, i64, u32, u64, usize, f32, f64);
+
+/// Which plural form a language uses for a count, named after the CLDR categories `zero`,
+/// `one`, `few`, `many` and `other`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PluralRule {
+    /// Japanese, Korean and Chinese don't change words for counts
+    Other,
+    /// English, German, Spanish and most other languages, `one` for 1
+    #[default]
+    OneOther,
+    /// French and Portuguese, `one` for 0 and 1
+    ZeroOneOther,
+    /// Russian and Ukrainian, `one` for 1, 21, 31, `few` for 2-4, 22-24, `many` for the rest
+    EastSlavic,
+    /// `one` for 1, `few` for 2-4, 22-24, `many` for the rest
+    Polish,
+}
+
+impl PluralRule {
+    /// Rule of the language of a locale code like `"en"`, `"pt-BR"` or `"ja_JP"`
+    pub fn for_locale(locale: &str) -> Self {
+        let language = locale.split(['-', '_']).next().unwrap_or("");
+
+        match language.to_lowercase().as_str() {
+            "ja" | "ko" | "zh" | "th" | "vi" | "id" => PluralRule::Other,
+            "fr" | "pt" => PluralRule::ZeroOneOther,
+            "ru" | "uk" | "be" => PluralRule::EastSlavic,
+            "pl" => PluralRule::Polish,
+            _ => PluralRule::OneOther,
+        }
+    }
+
+    pub fn category(&self, count: f64) -> &'static str {
+        // fractions use the `other` form in every supported language
+        if count.fract() != 0. {
+            return "other";
+        }
+
+        let n = count.abs() as u64;
+        match self {
+            PluralRule::Other => "other",
+            PluralRule::OneOther if n == 1 => "one",
+            PluralRule::ZeroOneOther if n <= 1 => "one",
+            PluralRule::EastSlavic if n % 10 == 1 && n % 100 != 11 => "one",
+            PluralRule::Polish if n == 1 => "one",
+            PluralRule::EastSlavic | PluralRule::Polish
+                if (2..=4).contains(&(n % 10)) && !(12..=14).contains(&(n % 100)) =>
+            {
+                "few"
+            }
+            PluralRule::EastSlavic | PluralRule::Polish => "many",
+            _ => "other",
+        }
+    }
+}
+
+/// A string of a locale file, plurals map categories to their forms like
+/// `{"one": "{count} item", "other": "{count} items"}`
+#[derive(Deserialize, Debug, Clone)]
+#[serde(untagged)]
+enum LocaleString {
+    Text(String),
+    Plural(SerdeMap<String, String>),
+}
+
+#[derive(Debug, Clone)]
+struct Locale {
+    strings: HashMap<String, LocaleString>,
+    plural_rule: PluralRule,
+}
+
+/// String tables of every loaded locale, looked up by key in the current one.
+///
+/// Locale files are RON maps from keys to strings, `{name}` is replaced with the argument of that
+/// name and `{{` writes a literal `{`:
+///
+/// ```ron
+/// {
+///     "menu.start": "Start",
+///     "shop.greeting": "Welcome back, {name}!",
+///     "inventory.count": {"one": "{count} item", "other": "{count} items"},
+/// }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Localization {
+    locales: HashMap<String, Locale>,
+    current: Option<String>,
+    /// Looked in for keys the current locale is missing
+    fallback: Option<String>,
+}
+
+impl Localization {
+    /// Adds or replaces the strings of `locale`, the first locale loaded becomes the current one
+    pub fn load_locale(&mut self, locale: &str, bytes: &[u8]) -> Result<(), SmolError> {
+        let strings: SerdeMap<String, LocaleString> = ron::de::from_bytes(bytes)?;
+        self.locales.insert(
+            locale.to_owned(),
+            Locale {
+                strings: strings.into_iter().collect(),
+                plural_rule: PluralRule::for_locale(locale),
+            },
+        );
+
+        if self.current.is_none() {
+            self.current = Some(locale.to_owned());
+        }
+
+        Ok(())
+    }
+
+    pub fn set_locale(&mut self, locale: &str) -> Result<(), SmolError> {
+        if !self.locales.contains_key(locale) {
+            return Err(SmolError::new(format!("Locale {} isn't loaded", locale)));
+        }
+        self.current = Some(locale.to_owned());
+
+        Ok(())
+    }
+
+    pub fn locale(&self) -> Option<&str> {
+        self.current.as_deref()
+    }
+
+    /// Codes of every loaded locale
+    pub fn locales(&self) -> impl Iterator<Item = &str> {
+        self.locales.keys().map(String::as_str)
+    }
+
+    /// Locale used for keys the current one doesn't have, usually the one written first
+    pub fn set_fallback_locale(&mut self, locale: Option<&str>) {
+        self.fallback = locale.map(str::to_owned);
+    }
+
+    /// Overrides the plural rule picked from the locale's code
+    pub fn set_plural_rule(&mut self, locale: &str, plural_rule: PluralRule) {
+        if let Some(locale) = self.locales.get_mut(locale) {
+            locale.plural_rule = plural_rule;
+        }
+    }
+
+    pub fn has_key(&self, key: &str) -> bool {
+        self.find(key).is_some()
+    }
+
+    /// The string of `key` in the current locale with its placeholders filled in, missing keys
+    /// return the key itself so they stand out without breaking the game
+    pub fn get(&self, key: &str, args: &[(&str, LocaleArg)]) -> String {
+        let (string, locale) = match self.find(key) {
+            Some(found) => found,
+            None => return key.to_owned(),
+        };
+
+        let template = match string {
+            LocaleString::Text(text) => text,
+            LocaleString::Plural(forms) => {
+                let category = args
+                    .iter()
+                    .find_map(|(name, arg)| match arg {
+                        LocaleArg::Number(count) if *name == "count" => Some(*count),
+                        _ => None,
+                    })
+                    .map_or("other", |count| locale.plural_rule.category(count));
+
+                match forms.get(category).or_else(|| forms.get("other")) {
+                    Some(form) => form,
+                    None => return key.to_owned(),
+                }
+            }
+        };
+
+        format_placeholders(template, args)
+    }
+
+    fn find(&self, key: &str) -> Option<(&LocaleString, &Locale)> {
+        [self.current.as_ref(), self.fallback.as_ref()]
+            .iter()
+            .flatten()
+            .filter_map(|code| self.locales.get(code.as_str()))
+            .find_map(|locale| locale.strings.get(key).map(|string| (string, locale)))
+    }
+}
+
+fn format_placeholders(template: &str, args: &[(&str, LocaleArg)]) -> String {
+    let mut formatted = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find('{') {
+        formatted.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        if rest.starts_with("{{") {
+            formatted.push('{');
+            rest = &rest[2..];
+            continue;
+        }
+
+        let end = match rest.find('}') {
+            Some(end) => end,
+            None => break,
+        };
+        let name = &rest[1..end];
+        match args.iter().find(|(arg_name, _)| *arg_name == name) {
+            Some((_, arg)) => {
+                let _ = write!(formatted, "{}", arg);
+            }
+            // unknown placeholders are kept so they show up while testing
+            None => formatted.push_str(&rest[..=end]),
+        }
+        rest = &rest[end + 1..];
+    }
+    formatted.push_str(rest);
+
+    formatted
+}
+
+impl App {
+    /// Loads a locale file named after its locale code, like `import_file!("../assets/en.ron")`
+    pub fn load_locale<'a>(&mut self, asset: Asset<'a>) -> Result<(), SmolError> {
+        self.renderer.localization.load_locale(asset.0, asset.2)
+    }
+}
+
+impl Renderer {
+    /// Draws the localized string of `key`, see `Localization::get`
+    pub fn text_key(
+        &mut self,
+        font: &Font,
+        key: &str,
+        args: &[(&str, LocaleArg)],
+        settings: &TextSettings,
+    ) {
+        let text = self.localization.get(key, args);
+        self.text_ex(font, &text, settings);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plural_rule_for_locale() {
+        assert_eq!(PluralRule::for_locale("en"), PluralRule::OneOther);
+        assert_eq!(PluralRule::for_locale("pt-BR"), PluralRule::ZeroOneOther);
+        assert_eq!(PluralRule::for_locale("ja_JP"), PluralRule::Other);
+        assert_eq!(PluralRule::for_locale("RU"), PluralRule::EastSlavic);
+        assert_eq!(PluralRule::for_locale("pl"), PluralRule::Polish);
+        assert_eq!(PluralRule::for_locale(""), PluralRule::OneOther);
+    }
+
+    #[test]
+    fn plural_categories() {
+        let categories = |rule: PluralRule, counts: &[f64]| -> Vec<&str> {
+            counts.iter().map(|count| rule.category(*count)).collect()
+        };
+
+        assert_eq!(
+            categories(PluralRule::OneOther, &[0., 1., 2., -1., 1.5]),
+            ["other", "one", "other", "one", "other"]
+        );
+        assert_eq!(
+            categories(PluralRule::ZeroOneOther, &[0., 1., 2.]),
+            ["one", "one", "other"]
+        );
+        assert_eq!(categories(PluralRule::Other, &[1., 2.]), ["other", "other"]);
+        assert_eq!(
+            categories(
+                PluralRule::EastSlavic,
+                &[1., 2., 5., 11., 12., 21., 22., 25., 111.]
+            ),
+            ["one", "few", "many", "many", "many", "one", "few", "many", "many"]
+        );
+        assert_eq!(
+            categories(PluralRule::Polish, &[1., 2., 5., 12., 21., 22.]),
+            ["one", "few", "many", "many", "many", "few"]
+        );
+    }
+
+    #[test]
+    fn placeholders_are_filled_in() {
+        let args = [("name", "Ada".into()), ("count", 3.into())];
+
+        assert_eq!(
+            format_placeholders("{name} has {count} keys", &args),
+            "Ada has 3 keys"
+        );
+        assert_eq!(format_placeholders("{{name} {name}", &args), "{name} Ada");
+        assert_eq!(format_placeholders("{missing}!", &args), "{missing}!");
+        assert_eq!(format_placeholders("open {name", &args), "open {name");
+        assert_eq!(format_placeholders("", &args), "");
+    }
+
+    #[test]
+    fn plural_strings_pick_their_form() {
+        let mut localization = Localization::default();
+        localization
+            .load_locale(
+                "en",
+                br#"{"items": {"one": "{count} item", "other": "{count} items"}, "hi": "Hi"}"#,
+            )
+            .unwrap();
+        localization
+            .load_locale(
+                "fr",
+                br#"{"items": {"one": "{count} objet", "other": "{count} objets"}}"#,
+            )
+            .unwrap();
+
+        assert_eq!(localization.get("items", &[("count", 1.into())]), "1 item");
+        assert_eq!(localization.get("items", &[("count", 0.into())]), "0 items");
+        assert_eq!(localization.get("missing", &[]), "missing");
+
+        localization.set_locale("fr").unwrap();
+        localization.set_fallback_locale(Some("en"));
+        assert_eq!(localization.get("items", &[("count", 0.into())]), "0 objet");
+        assert_eq!(localization.get("hi", &[]), "Hi");
+    }
+}
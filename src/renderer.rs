@@ -1,10 +1,15 @@
 pub(crate) const MAX_BATCH_SIZE: i32 = 10000;
 
 pub mod batch;
+pub(crate) mod bitmap_font;
 pub mod core;
+pub mod rich_text;
+pub(crate) mod sdf_font;
 pub mod shader;
 pub mod shapes;
 pub mod text;
+pub mod text_effects;
+pub(crate) mod text_layout;
 pub(crate) mod texture;
 
 pub use self::core::*;
@@ -6,24 +6,38 @@ use super::{Anchor, Renderer, Texture, MAX_BATCH_SIZE};
 
 impl Renderer {
     pub fn flush_batch(&mut self) {
+        self.apply_viewport();
+
         if !self.verticies.is_empty() {
+            let is_sdf = self
+                .current_batch_id
+                .as_ref()
+                .is_some_and(|id| id.starts_with("sdf_"));
+
             self.context.render(
                 &self.verticies,
                 &self.indicies,
                 &self.bound_texture_map,
-                &self.camera.get_projection_view_matrix(self.render_size),
+                &self.batch_projection(),
+                Some(&self.sdf_uniforms).filter(|_| is_sdf),
+                self.bound_shader
+                    .map(|shader| shader.id)
+                    .filter(|id| *id != 0),
             );
         }
 
-        self.render_all_text_queue().unwrap();
+        let text_projection = self.batch_projection();
+        // text that doesn't fit in the glyph cache at once is drawn in pieces, see `take_text_error`
+        if let Err(error) = self.render_all_text_queue(&text_projection) {
+            self.text_error = Some(error);
+        }
 
         self.verticies.clear();
         self.indicies.clear();
         self.bound_texture_map.clear();
-        self.bound_shader = None;
     }
 
-    fn check_batch_overflow(&mut self) {
+    pub(crate) fn check_batch_overflow(&mut self) {
         let max_vertex_amount = 4 * MAX_BATCH_SIZE as usize;
         let max_indicies_amount = 6 * MAX_BATCH_SIZE as usize;
 
@@ -48,8 +62,49 @@ impl Renderer {
         anchor: Anchor,
         texture: Option<&Texture>,
     ) {
-        self.check_batch_overflow();
+        if !self.is_layer_visible() {
+            return;
+        }
+
         self.set_batch_id("rect");
+
+        let anchor_point = get_anchor_point(anchor, size);
+        let anchor = Point3::from([anchor_point.x * scale.x, anchor_point.y * scale.y, 0.0]);
+
+        let model = Matrix4::new_translation(&Vector::from([
+            position.x - anchor_point.x,
+            position.y - anchor_point.y,
+            position.z,
+        ])) * Matrix4::new_rotation_wrt_point(rotation, anchor)
+            * Matrix4::new_nonuniform_scaling(&Vector::from([
+                size.x * scale.x,
+                size.y * scale.y,
+                1.0,
+            ]));
+
+        let tex_coords = if let Some(texture) = texture {
+            texture.get_tex_coords()
+        } else {
+            [[1., 1.], [1., 0.], [0., 0.], [0., 1.]]
+        };
+
+        self.push_quad(&model, tex_coords, color, texture);
+    }
+
+    /// Pushes the unit square transformed by `model`, `tex_coords` go top right, bottom right,
+    /// bottom left then top left like `Texture::get_tex_coords`
+    pub(crate) fn push_quad(
+        &mut self,
+        model: &Matrix4<f32>,
+        tex_coords: [[f32; 2]; 4],
+        color: [f32; 4],
+        texture: Option<&Texture>,
+    ) {
+        if texture.is_some_and(|texture| !texture.is_alive()) {
+            return;
+        }
+
+        self.check_batch_overflow();
         let verticies_amount: i32 = self.verticies.len() as _;
 
         let mut new_indicies: Vec<i32> = vec![
@@ -65,37 +120,15 @@ impl Renderer {
 
         self.indicies.append(&mut new_indicies);
 
-        let (top_right, bottom_right, bottom_left, top_left) = {
-            let anchor_point = get_anchor_point(anchor, size);
-            let anchor = Point3::from([anchor_point.x * scale.x, anchor_point.y * scale.y, 0.0]);
-
-            let model = Matrix4::new_translation(&Vector::from([
-                position.x - anchor_point.x,
-                position.y - anchor_point.y,
-                1.,
-            ])) * Matrix4::new_rotation_wrt_point(rotation, anchor)
-                * Matrix4::new_nonuniform_scaling(&Vector::from([
-                    size.x * scale.x,
-                    size.y * scale.y,
-                    1.0,
-                ]));
-
-            (
-                model * Vector::from([1., 1., 0., 1.]),
-                model * Vector::from([1., 0., 0., 1.]),
-                model * Vector::from([0., 0., 0., 1.]),
-                model * Vector::from([0., 1., 0., 1.]),
-            )
-        };
+        let (top_right, bottom_right, bottom_left, top_left) = (
+            model * Vector::from([1., 1., 0., 1.]),
+            model * Vector::from([1., 0., 0., 1.]),
+            model * Vector::from([0., 0., 0., 1.]),
+            model * Vector::from([0., 1., 0., 1.]),
+        );
 
         let tex_index = self.get_texture_index(texture);
 
-        let tex_coords = if let Some(texture) = texture {
-            texture.get_tex_coords()
-        } else {
-            [[1., 1.], [1., 0.], [0., 0.], [0., 1.]]
-        };
-
         let mut new_verticies = vec![
             Vertex {
                 position: top_right,
@@ -0,0 +1,877 @@
+use std::str::FromStr;
+
+use hashbrown::HashMap;
+use nalgebra::{Matrix4, Vector, Vector2};
+
+use crate::asset_store::Asset;
+use crate::errors::SmolError;
+use crate::gfx::GfxContext;
+
+use super::shapes::Rectangle;
+use super::text::{
+    text_transform_matrix, LineMetrics, TextAlignment, TextOverflow, TextSettings, TextSpace,
+    TextVerticalAlignment, TextWrap,
+};
+use super::text_effects::{glyph_color, transform_rect, TextGlyph};
+use super::{Font, Renderer, Texture};
+
+/// A run of text sharing one colour
+pub(crate) type TextRun<'a> = (&'a str, [f32; 4]);
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct BitmapGlyph {
+    /// Image of the glyph on its page
+    pub texture: Texture,
+    /// Offset of the image from the caret at the top of the line
+    pub offset: Vector2<f32>,
+    pub advance: f32,
+}
+
+/// Font drawn from pre-rendered glyph images instead of outlines, all sizes are in pixels of
+/// the images
+#[derive(Debug, Default)]
+pub(crate) struct BitmapFont {
+    pub glyphs: HashMap<char, BitmapGlyph>,
+    pub kerning: HashMap<(char, char), f32>,
+    pub line_height: f32,
+    /// Distance from the top of a line to the baseline
+    pub base: f32,
+}
+
+/// The font owns its page textures, they're deleted with it
+impl Drop for BitmapFont {
+    fn drop(&mut self) {
+        let mut pages: Vec<u32> = self.glyphs.values().map(|glyph| glyph.texture.id).collect();
+        pages.sort_unstable();
+        pages.dedup();
+
+        for page in pages {
+            GfxContext::delete_texture(page);
+        }
+    }
+}
+
+impl BitmapFont {
+    /// Files of the page images a BMFont descriptor names, relative to the descriptor
+    pub fn page_files(descriptor: &[u8]) -> Result<Vec<String>, SmolError> {
+        Ok(BmFontDescriptor::parse(descriptor)?
+            .pages
+            .into_iter()
+            .map(|(_, file)| file.to_owned())
+            .collect())
+    }
+
+    /// Reads an AngelCode BMFont descriptor in the text or XML format, `pages` are matched to the
+    /// descriptor's page files by name, falling back to their order
+    pub fn from_bmfont(descriptor: &[u8], pages: &[Asset]) -> Result<Self, SmolError> {
+        let descriptor = BmFontDescriptor::parse(descriptor)?;
+
+        let mut page_textures: HashMap<usize, Texture> = HashMap::new();
+        for (id, file) in &descriptor.pages {
+            match load_page(pages, *id, file) {
+                Ok(texture) => page_textures.insert(*id, texture),
+                Err(error) => {
+                    for texture in page_textures.values() {
+                        GfxContext::delete_texture(texture.id);
+                    }
+                    return Err(error);
+                }
+            };
+        }
+
+        let glyphs = descriptor
+            .chars
+            .iter()
+            .map(|record| {
+                let glyph = BitmapGlyph {
+                    texture: page_textures[&record.page].region(record.position, record.size),
+                    offset: record.offset,
+                    advance: record.advance,
+                };
+
+                (record.c, glyph)
+            })
+            .collect();
+
+        Ok(BitmapFont {
+            glyphs,
+            kerning: descriptor.kerning,
+            line_height: descriptor.line_height,
+            base: descriptor.base,
+        })
+    }
+
+    /// Cuts `image` into cells of `cell_size`, one for each of `chars` read left to right and
+    /// top to bottom
+    pub fn from_grid(
+        image: Asset,
+        cell_size: Vector2<f32>,
+        chars: &str,
+    ) -> Result<Self, SmolError> {
+        if cell_size.x <= 0. || cell_size.y <= 0. {
+            return Err(SmolError::new("Grid font cells need a width and height"));
+        }
+
+        let (width, height, texture_id) = GfxContext::generate_texture(image.2, image.1)?;
+        let size = Vector::from([width as f32, height as f32]);
+        let texture = Texture::new(texture_id, size, Vector2::default(), size);
+        let columns = ((texture.uv_size.x / cell_size.x) as usize).max(1);
+        let glyphs = chars
+            .chars()
+            .enumerate()
+            .map(|(index, c)| {
+                let position = Vector::from([
+                    (index % columns) as f32 * cell_size.x,
+                    (index / columns) as f32 * cell_size.y,
+                ]);
+                let glyph = BitmapGlyph {
+                    texture: texture.region(position, cell_size),
+                    offset: Vector2::default(),
+                    advance: cell_size.x,
+                };
+
+                (c, glyph)
+            })
+            .collect();
+
+        Ok(BitmapFont {
+            glyphs,
+            kerning: HashMap::new(),
+            line_height: cell_size.y,
+            base: cell_size.y,
+        })
+    }
+
+    fn advance(&self, c: char) -> f32 {
+        self.glyphs.get(&c).map_or(0., |glyph| glyph.advance)
+    }
+
+    /// Places `runs` the same way glyph_brush lays out vector text with `settings`
+    pub fn layout(&self, runs: &[TextRun], scale: f32, settings: &TextSettings) -> Vec<PlacedLine> {
+        let (width, height) = match settings.bounds {
+            Some(bounds) => (bounds.width, bounds.height),
+            None => (settings.max_width.unwrap_or(f32::INFINITY), f32::INFINITY),
+        };
+        let wrap_width = if settings.wrap == TextWrap::None {
+            f32::INFINITY
+        } else {
+            width
+        };
+
+        let next_x = |line: &[PlacedChar], c: char| {
+            line.last().map_or(0., |previous| {
+                let kerning = self.kerning.get(&(previous.c, c)).copied().unwrap_or(0.);
+                previous.x + previous.advance + kerning * scale + settings.letter_spacing
+            })
+        };
+
+        let mut lines: Vec<(usize, Vec<PlacedChar>)> = vec![(0, Vec::new())];
+        let mut char_index = 0;
+        for (text, color) in runs {
+            for c in text.chars() {
+                let index = char_index;
+                char_index += 1;
+
+                if c == '\n' {
+                    lines.push((index + 1, Vec::new()));
+                    continue;
+                }
+
+                let advance = self.advance(c) * scale;
+                let line = &mut lines.last_mut().unwrap().1;
+                if !line.is_empty() && !c.is_whitespace() && next_x(line, c) + advance > wrap_width
+                {
+                    let split = match settings.wrap {
+                        TextWrap::Word => line
+                            .iter()
+                            .rposition(|placed| placed.c.is_whitespace())
+                            .map(|space| space + 1),
+                        TextWrap::Char | TextWrap::None => None,
+                    };
+                    let mut wrapped = line.split_off(split.unwrap_or(line.len()));
+                    let shift = wrapped.first().map_or(0., |placed| placed.x);
+                    for placed in &mut wrapped {
+                        placed.x -= shift;
+                    }
+                    lines.push((wrapped.first().map_or(index, |p| p.char_index), wrapped));
+                }
+
+                let line = &mut lines.last_mut().unwrap().1;
+                let x = next_x(line, c);
+                line.push(PlacedChar {
+                    c,
+                    char_index: index,
+                    x,
+                    advance,
+                    color: *color,
+                });
+            }
+        }
+
+        let line_count = lines.len();
+        if let Some(max_lines) = settings.max_lines {
+            lines.truncate(max_lines);
+        }
+
+        let line_height = self.line_height * scale;
+        let line_advance = line_height * settings.line_height;
+        match settings.overflow {
+            TextOverflow::Visible => {}
+            TextOverflow::Clip => {
+                let visible = (height / line_advance).ceil().max(0.) as usize;
+                lines.truncate(visible);
+            }
+            TextOverflow::Ellipsis => {
+                let visible = ((height - line_height) / line_advance + 1.).floor().max(0.);
+                lines.truncate(visible as usize);
+            }
+        }
+
+        if settings.overflow == TextOverflow::Ellipsis {
+            let truncated = lines.len() < line_count;
+            let last_index = lines.len().saturating_sub(1);
+
+            for (index, (_, line)) in lines.iter_mut().enumerate() {
+                if line_width(line) > width || (truncated && index == last_index) {
+                    self.ellipsize(line, width, scale, settings.letter_spacing);
+                }
+            }
+        }
+
+        let anchor = settings.anchor();
+        let block_height = match lines.len() {
+            0 => 0.,
+            count => (count - 1) as f32 * line_advance + line_height,
+        };
+        let top = anchor.y
+            - match settings.vertical_alignment {
+                TextVerticalAlignment::Top => 0.,
+                TextVerticalAlignment::Center => block_height / 2.,
+                TextVerticalAlignment::Bottom => block_height,
+            };
+
+        lines
+            .into_iter()
+            .enumerate()
+            .map(|(index, (start, mut chars))| {
+                let width = line_width(&chars);
+                let x = anchor.x
+                    - match settings.alignment {
+                        TextAlignment::Left => 0.,
+                        TextAlignment::Center => width / 2.,
+                        TextAlignment::Right => width,
+                    };
+                for placed in &mut chars {
+                    placed.x += x;
+                }
+                let top = top + index as f32 * line_advance;
+
+                PlacedLine {
+                    end: chars.last().map_or(start, |placed| placed.char_index + 1),
+                    start,
+                    chars,
+                    x,
+                    width,
+                    top,
+                    height: line_height,
+                    baseline: top + self.base * scale,
+                }
+            })
+            .collect()
+    }
+
+    /// Removes trailing characters until the line and an ellipsis fit in `max_width`
+    fn ellipsize(
+        &self,
+        line: &mut Vec<PlacedChar>,
+        max_width: f32,
+        scale: f32,
+        letter_spacing: f32,
+    ) {
+        let template = match line.last() {
+            Some(placed) => *placed,
+            None => return,
+        };
+        let ellipsis = if self.glyphs.contains_key(&'…') {
+            vec!['…']
+        } else {
+            vec!['.'; 3]
+        };
+        let ellipsis_width = ellipsis
+            .iter()
+            .map(|c| self.advance(*c) * scale)
+            .sum::<f32>()
+            + letter_spacing * (ellipsis.len() as f32 - 1.);
+
+        while let Some(last) = line.last() {
+            if last.x + last.advance + letter_spacing + ellipsis_width <= max_width
+                && !last.c.is_whitespace()
+            {
+                break;
+            }
+            line.pop();
+        }
+
+        let mut x = line
+            .last()
+            .map_or(0., |last| last.x + last.advance + letter_spacing);
+        for c in ellipsis {
+            let advance = self.advance(c) * scale;
+            line.push(PlacedChar {
+                c,
+                x,
+                advance,
+                ..template
+            });
+            x += advance + letter_spacing;
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct PlacedChar {
+    pub c: char,
+    /// Index of the character in the laid out text
+    pub char_index: usize,
+    /// Position of the caret before the character
+    pub x: f32,
+    pub advance: f32,
+    pub color: [f32; 4],
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct PlacedLine {
+    pub chars: Vec<PlacedChar>,
+    /// Index of the first character of the line, even when it has none
+    pub start: usize,
+    /// Index after the last character of the line
+    pub end: usize,
+    pub x: f32,
+    pub width: f32,
+    pub top: f32,
+    pub height: f32,
+    pub baseline: f32,
+}
+
+impl PlacedLine {
+    fn bounds(&self) -> Rectangle {
+        Rectangle {
+            x: self.x,
+            y: self.top,
+            width: self.width,
+            height: self.height,
+        }
+    }
+}
+
+/// Width of a line without its trailing whitespace
+fn line_width(line: &[PlacedChar]) -> f32 {
+    line.iter()
+        .rev()
+        .find(|placed| !placed.c.is_whitespace())
+        .map_or(0., |last| last.x + last.advance - line[0].x)
+}
+
+fn lines_bounds(lines: &[PlacedLine], settings: &TextSettings) -> Rectangle {
+    let (first, last) = match (lines.first(), lines.last()) {
+        (Some(first), Some(last)) => (first, last),
+        _ => {
+            let anchor = settings.anchor();
+            return Rectangle {
+                x: anchor.x,
+                y: anchor.y,
+                width: 0.,
+                height: 0.,
+            };
+        }
+    };
+
+    let min_x = lines
+        .iter()
+        .map(|line| line.x)
+        .fold(f32::INFINITY, f32::min);
+    let max_x = lines
+        .iter()
+        .map(|line| line.x + line.width)
+        .fold(f32::NEG_INFINITY, f32::max);
+
+    Rectangle {
+        x: min_x,
+        y: first.top,
+        width: max_x - min_x,
+        height: last.top + last.height - first.top,
+    }
+}
+
+fn load_page(pages: &[Asset], id: usize, file: &str) -> Result<Texture, SmolError> {
+    let name = file.rsplit(['/', '\\']).next().unwrap_or(file);
+    let name = name.rsplitn(2, '.').last().unwrap_or(name);
+    let page = pages
+        .iter()
+        .find(|page| page.0 == name)
+        .or_else(|| pages.get(id))
+        .ok_or_else(|| SmolError::new(format!("Missing BMFont page {}", file)))?;
+
+    let (width, height, texture_id) = GfxContext::generate_texture(page.2, page.1)?;
+    let size = Vector::from([width as f32, height as f32]);
+
+    Ok(Texture::new(texture_id, size, Vector2::default(), size))
+}
+
+/// What a BMFont descriptor says, read before any of its pages are uploaded
+#[derive(Debug, Default, PartialEq)]
+struct BmFontDescriptor<'a> {
+    line_height: f32,
+    base: f32,
+    /// Ids and files of the pages
+    pages: Vec<(usize, &'a str)>,
+    chars: Vec<BmFontChar>,
+    kerning: HashMap<(char, char), f32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct BmFontChar {
+    c: char,
+    page: usize,
+    /// Position and size of the image on its page
+    position: Vector2<f32>,
+    size: Vector2<f32>,
+    offset: Vector2<f32>,
+    advance: f32,
+}
+
+impl<'a> BmFontDescriptor<'a> {
+    fn parse(descriptor: &'a [u8]) -> Result<Self, SmolError> {
+        if descriptor.starts_with(b"BMF") {
+            return Err(SmolError::new(
+                "Binary BMFont descriptors aren't supported, export as text or XML",
+            ));
+        }
+
+        let descriptor =
+            std::str::from_utf8(descriptor).map_err(|e| SmolError::new(e.to_string()))?;
+        let mut parsed = BmFontDescriptor::default();
+        let mut chars = Vec::new();
+        let mut kernings = Vec::new();
+
+        for (tag, attributes) in bmfont_records(descriptor) {
+            match tag {
+                "common" => {
+                    parsed.line_height = attribute(&attributes, tag, "lineHeight")?;
+                    parsed.base = attribute(&attributes, tag, "base")?;
+                }
+                "page" => {
+                    let id = attribute(&attributes, tag, "id")?;
+                    let file = attributes
+                        .get("file")
+                        .ok_or_else(|| SmolError::new("BMFont page is missing a file"))?;
+                    parsed.pages.push((id, *file));
+                }
+                "char" => chars.push(attributes),
+                "kerning" => kernings.push(attributes),
+                _ => {}
+            }
+        }
+
+        if parsed.line_height <= 0. {
+            return Err(SmolError::new("BMFont lineHeight has to be above 0"));
+        }
+
+        for attributes in chars {
+            let id: u32 = attribute(&attributes, "char", "id")?;
+            let c = match std::char::from_u32(id) {
+                Some(c) => c,
+                None => continue,
+            };
+            let page: usize = attribute(&attributes, "char", "page").unwrap_or(0);
+            if !parsed.pages.iter().any(|(id, _)| *id == page) {
+                return Err(SmolError::new(format!(
+                    "BMFont char {} uses a missing page",
+                    id
+                )));
+            }
+
+            parsed.chars.push(BmFontChar {
+                c,
+                page,
+                position: Vector::from([
+                    attribute(&attributes, "char", "x")?,
+                    attribute(&attributes, "char", "y")?,
+                ]),
+                size: Vector::from([
+                    attribute(&attributes, "char", "width")?,
+                    attribute(&attributes, "char", "height")?,
+                ]),
+                offset: Vector::from([
+                    attribute(&attributes, "char", "xoffset")?,
+                    attribute(&attributes, "char", "yoffset")?,
+                ]),
+                advance: attribute(&attributes, "char", "xadvance")?,
+            });
+        }
+
+        for attributes in kernings {
+            let first = std::char::from_u32(attribute(&attributes, "kerning", "first")?);
+            let second = std::char::from_u32(attribute(&attributes, "kerning", "second")?);
+            if let (Some(first), Some(second)) = (first, second) {
+                parsed.kerning.insert(
+                    (first, second),
+                    attribute(&attributes, "kerning", "amount")?,
+                );
+            }
+        }
+
+        Ok(parsed)
+    }
+}
+
+/// Splits a BMFont descriptor into its tags and their attributes, the text format has one tag
+/// per line and the XML format one per element
+fn bmfont_records(descriptor: &str) -> Vec<(&str, HashMap<&str, &str>)> {
+    let records: Vec<&str> = if descriptor.trim_start().starts_with('<') {
+        descriptor
+            .split('<')
+            .filter_map(|element| element.split('>').next())
+            .filter(|element| !element.starts_with(['/', '?', '!']))
+            .map(|element| element.trim_end_matches('/'))
+            .collect()
+    } else {
+        descriptor.lines().collect()
+    };
+
+    records
+        .into_iter()
+        .filter_map(|record| {
+            let record = record.trim();
+            let tag_end = record.find(char::is_whitespace).unwrap_or(record.len());
+            let (tag, mut rest) = record.split_at(tag_end);
+            if tag.is_empty() {
+                return None;
+            }
+
+            let mut attributes = HashMap::new();
+            while let Some(equals) = rest.find('=') {
+                let key = rest[..equals].trim();
+                rest = rest[equals + 1..].trim_start();
+
+                let value = if let Some(quoted) = rest.strip_prefix('"') {
+                    let end = quoted.find('"').unwrap_or(quoted.len());
+                    rest = quoted.get(end + 1..).unwrap_or("");
+                    &quoted[..end]
+                } else {
+                    let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+                    let value = &rest[..end];
+                    rest = &rest[end..];
+                    value
+                };
+                attributes.insert(key, value);
+            }
+
+            Some((tag, attributes))
+        })
+        .collect()
+}
+
+fn attribute<T: FromStr>(
+    attributes: &HashMap<&str, &str>,
+    tag: &str,
+    key: &str,
+) -> Result<T, SmolError> {
+    attributes
+        .get(key)
+        .and_then(|value| value.parse().ok())
+        .ok_or_else(|| SmolError::new(format!("BMFont {} is missing a valid {}", tag, key)))
+}
+
+impl Renderer {
+    fn bitmap_layout(
+        &self,
+        font: &Font,
+        runs: &[TextRun],
+        settings: &TextSettings,
+    ) -> Vec<PlacedLine> {
+        if !self.font_cache.is_alive(font) {
+            return Vec::new();
+        }
+
+        let bitmap_font = &self.font_cache.bitmap_fonts[font.id];
+        let scale = settings.size.unwrap_or(font.size) / bitmap_font.line_height;
+
+        bitmap_font.layout(runs, scale, settings)
+    }
+
+    pub(crate) fn bitmap_text(&mut self, font: &Font, runs: &[TextRun], settings: &TextSettings) {
+        let lines = self.bitmap_layout(font, runs, settings);
+        let bitmap_font = &self.font_cache.bitmap_fonts[font.id];
+        let scale = settings.size.unwrap_or(font.size) / bitmap_font.line_height;
+        let model = settings
+            .transform
+            .map_or_else(Matrix4::identity, |transform| {
+                text_transform_matrix(lines_bounds(&lines, settings), &transform)
+            });
+        let clip = settings
+            .bounds
+            .filter(|_| settings.overflow != TextOverflow::Visible);
+
+        let mut glyphs = Vec::new();
+        for line in &lines {
+            for placed in &line.chars {
+                let transform = match settings.transform_glyph(&TextGlyph {
+                    index: placed.char_index,
+                    c: placed.c,
+                    position: Vector::from([placed.x, line.baseline]),
+                }) {
+                    Some(transform) => transform,
+                    None => continue,
+                };
+                let glyph = match bitmap_font.glyphs.get(&placed.c) {
+                    Some(glyph) => glyph,
+                    None => continue,
+                };
+                if glyph.texture.uv_size.x <= 0. || glyph.texture.uv_size.y <= 0. {
+                    continue;
+                }
+                let rect = Rectangle {
+                    x: placed.x + glyph.offset.x * scale,
+                    y: line.top + glyph.offset.y * scale,
+                    width: glyph.texture.uv_size.x * scale,
+                    height: glyph.texture.uv_size.y * scale,
+                };
+                glyphs.push((
+                    transform_rect(rect, &transform),
+                    placed.color,
+                    transform,
+                    glyph.texture,
+                ));
+            }
+        }
+
+        // copies for the shadow and outline go behind the text
+        let mut quads = Vec::new();
+        for (offset, color) in settings.effect_passes() {
+            for (rect, _, transform, texture) in &glyphs {
+                let rect = Rectangle {
+                    x: rect.x + offset.x,
+                    y: rect.y + offset.y,
+                    ..*rect
+                };
+                if let Some((rect, part)) = clip_rect(rect, clip) {
+                    let mut color = color;
+                    color[3] *= transform.alpha;
+                    quads.push((rect, part, color, *texture));
+                }
+            }
+        }
+        for (rect, color, transform, texture) in glyphs {
+            if let Some((rect, part)) = clip_rect(rect, clip) {
+                quads.push((rect, part, glyph_color(color, &transform), texture));
+            }
+        }
+
+        match settings.space {
+            TextSpace::Screen => self.set_batch_id("bitmap_text"),
+            TextSpace::World => self.set_batch_id("bitmap_world_text"),
+        }
+
+        for (rect, part, color, texture) in quads {
+            let quad_model = model
+                * Matrix4::new_translation(&Vector::from([rect.x, rect.y, 0.]))
+                * Matrix4::new_nonuniform_scaling(&Vector::from([rect.width, rect.height, 1.]));
+            self.push_quad(
+                &quad_model,
+                texture.get_sub_tex_coords(part),
+                color,
+                Some(&texture),
+            );
+        }
+    }
+
+    pub(crate) fn measure_bitmap_text(
+        &self,
+        font: &Font,
+        runs: &[TextRun],
+        settings: &TextSettings,
+    ) -> Rectangle {
+        lines_bounds(&self.bitmap_layout(font, runs, settings), settings)
+    }
+
+    pub(crate) fn bitmap_text_lines(
+        &self,
+        font: &Font,
+        text: &str,
+        settings: &TextSettings,
+    ) -> Vec<LineMetrics> {
+        self.bitmap_layout(font, &[(text, settings.color.normalize())], settings)
+            .into_iter()
+            .filter(|line| !line.chars.is_empty())
+            .map(|line| LineMetrics {
+                bounds: line.bounds(),
+                baseline: line.baseline,
+                chars: line.start..line.end,
+            })
+            .collect()
+    }
+
+    pub(crate) fn bitmap_caret_position(
+        &self,
+        font: &Font,
+        text: &str,
+        settings: &TextSettings,
+        char_index: usize,
+    ) -> Rectangle {
+        let lines = self.bitmap_layout(font, &[(text, settings.color.normalize())], settings);
+        let line = match lines.iter().rev().find(|line| line.start <= char_index) {
+            Some(line) => line,
+            None => return lines_bounds(&lines, settings),
+        };
+
+        let x = line
+            .chars
+            .iter()
+            .find(|placed| placed.char_index == char_index)
+            .map(|placed| placed.x)
+            .or_else(|| line.chars.last().map(|last| last.x + last.advance))
+            .unwrap_or(line.x);
+
+        Rectangle {
+            x,
+            y: line.top,
+            width: 0.,
+            height: line.height,
+        }
+    }
+}
+
+/// Cuts `rect` down to `clip`, returning what's left and which part of the original that is
+pub(crate) fn clip_rect(
+    rect: Rectangle,
+    clip: Option<Rectangle>,
+) -> Option<(Rectangle, Rectangle)> {
+    let full = Rectangle {
+        x: 0.,
+        y: 0.,
+        width: 1.,
+        height: 1.,
+    };
+    let clip = match clip {
+        Some(clip) => clip,
+        None => return Some((rect, full)),
+    };
+
+    let min_x = rect.x.max(clip.x);
+    let min_y = rect.y.max(clip.y);
+    let max_x = (rect.x + rect.width).min(clip.x + clip.width);
+    let max_y = (rect.y + rect.height).min(clip.y + clip.height);
+    if min_x >= max_x || min_y >= max_y {
+        return None;
+    }
+
+    Some((
+        Rectangle {
+            x: min_x,
+            y: min_y,
+            width: max_x - min_x,
+            height: max_y - min_y,
+        },
+        Rectangle {
+            x: (min_x - rect.x) / rect.width,
+            y: (min_y - rect.y) / rect.height,
+            width: (max_x - min_x) / rect.width,
+            height: (max_y - min_y) / rect.height,
+        },
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEXT_DESCRIPTOR: &str = r#"info face="Pixel" size=16 bold=0 padding=0,0,0,0
+common lineHeight=18 base=14 scaleW=64 scaleH=64 pages=2
+page id=0 file="fonts/pixel_0.png"
+page id=1 file="pixel_1.png"
+chars count=2
+char id=65   x=0     y=0     width=8     height=10    xoffset=1     yoffset=4     xadvance=9     page=0  chnl=15
+char id=66   x=8     y=2     width=7     height=10    xoffset=0     yoffset=-1    xadvance=8     page=1  chnl=15
+kernings count=1
+kerning first=65 second=66 amount=-2
+"#;
+
+    const XML_DESCRIPTOR: &str = r#"<?xml version="1.0"?>
+<font>
+  <info face="Pixel" size="16"/>
+  <common lineHeight="18" base="14" scaleW="64" scaleH="64" pages="2"/>
+  <pages>
+    <page id="0" file="fonts/pixel_0.png" />
+    <page id="1" file="pixel_1.png" />
+  </pages>
+  <chars count="2">
+    <char id="65" x="0" y="0" width="8" height="10" xoffset="1" yoffset="4" xadvance="9" page="0" chnl="15" />
+    <char id="66" x="8" y="2" width="7" height="10" xoffset="0" yoffset="-1" xadvance="8" page="1" chnl="15" />
+  </chars>
+  <kernings count="1">
+    <kerning first="65" second="66" amount="-2" />
+  </kernings>
+</font>
+"#;
+
+    fn expected() -> BmFontDescriptor<'static> {
+        BmFontDescriptor {
+            line_height: 18.,
+            base: 14.,
+            pages: vec![(0, "fonts/pixel_0.png"), (1, "pixel_1.png")],
+            chars: vec![
+                BmFontChar {
+                    c: 'A',
+                    page: 0,
+                    position: Vector::from([0., 0.]),
+                    size: Vector::from([8., 10.]),
+                    offset: Vector::from([1., 4.]),
+                    advance: 9.,
+                },
+                BmFontChar {
+                    c: 'B',
+                    page: 1,
+                    position: Vector::from([8., 2.]),
+                    size: Vector::from([7., 10.]),
+                    offset: Vector::from([0., -1.]),
+                    advance: 8.,
+                },
+            ],
+            kerning: [(('A', 'B'), -2.)].iter().copied().collect(),
+        }
+    }
+
+    #[test]
+    fn reads_text_descriptors() {
+        let descriptor = BmFontDescriptor::parse(TEXT_DESCRIPTOR.as_bytes()).unwrap();
+        assert_eq!(descriptor, expected());
+    }
+
+    #[test]
+    fn reads_xml_descriptors() {
+        let descriptor = BmFontDescriptor::parse(XML_DESCRIPTOR.as_bytes()).unwrap();
+        assert_eq!(descriptor, expected());
+    }
+
+    #[test]
+    fn lists_page_files() {
+        assert_eq!(
+            BitmapFont::page_files(TEXT_DESCRIPTOR.as_bytes()).unwrap(),
+            ["fonts/pixel_0.png", "pixel_1.png"]
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_descriptors() {
+        let binary = b"BMF\x03";
+        let missing_page = "common lineHeight=18 base=14\nchar id=65 x=0 y=0 width=8 height=10 xoffset=0 yoffset=0 xadvance=9 page=0";
+        let missing_line_height = "common base=14";
+        let zero_line_height = "common lineHeight=0 base=14";
+
+        assert!(BmFontDescriptor::parse(binary).is_err());
+        assert!(BmFontDescriptor::parse(missing_page.as_bytes()).is_err());
+        assert!(BmFontDescriptor::parse(missing_line_height.as_bytes()).is_err());
+        assert!(BmFontDescriptor::parse(zero_line_height.as_bytes()).is_err());
+    }
+}
@@ -1,14 +1,20 @@
-use crate::gfx::GfxContext;
-use crate::{camera::Camera, transform::Transform};
-use glyph_brush::GlyphBrush;
-use hashbrown::HashMap;
+use crate::errors::SmolError;
+use crate::gfx::{GfxContext, RenderTarget, SdfUniforms};
+use crate::localization::Localization;
+use crate::renderer::shapes::Rectangle;
+use crate::AppSettings;
+use crate::{
+    camera::{Camera, RenderLayers},
+    transform::Transform,
+};
 use sdl2::video::Window;
 
 use crate::color::Color;
 
-use nalgebra::{Vector, Vector2, Vector4};
+use nalgebra::{Matrix4, Vector, Vector2, Vector4};
 
-use crate::renderer::texture::{Font, Texture};
+use crate::renderer::text::FontCache;
+use crate::renderer::texture::Texture;
 
 use super::shader::Shader;
 #[allow(dead_code)]
@@ -32,6 +38,33 @@ impl Default for Anchor {
     }
 }
 
+/// How a virtual resolution is fitted into the window
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ScalingMode {
+    /// Fills the window, ignoring the aspect ratio
+    Stretch,
+    /// Largest scale that fits with the aspect ratio kept, bars fill the rest
+    #[default]
+    Letterbox,
+    /// Largest whole number scale that fits, keeps pixel art crisp
+    IntegerScale,
+    /// Keeps the scale of `Letterbox` but grows the virtual resolution to fill the window
+    Expand,
+}
+
+/// How the render size follows the window when no virtual resolution is set
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ResizePolicy {
+    /// One world unit stays one pixel, a bigger window shows more of the world
+    #[default]
+    KeepPixelScale,
+    /// The area visible at the starting size stays visible, the other axis grows to keep
+    /// the window's aspect ratio
+    KeepWorldUnits,
+    /// Always shows this many world units vertically, the width follows the aspect ratio
+    FixedHeight(i32),
+}
+
 #[derive(Default)]
 pub struct Renderer {
     pub(crate) context: GfxContext,
@@ -42,38 +75,240 @@ pub struct Renderer {
     pub(crate) bound_shader: Option<Shader>,
     pub(crate) current_batch_id: Option<String>,
     pub camera: Camera,
-    pub(crate) glyph_brushs: HashMap<Font, GlyphBrush<[f32; 13]>>,
+    pub(crate) font_cache: FontCache,
     pub(crate) render_size: Vector2<i32>,
+    pub(crate) window_size: Vector2<i32>,
+    pub(crate) layer: u8,
+    pub(crate) virtual_resolution: Option<Vector2<i32>>,
+    pub(crate) scaling: ScalingMode,
+    pub(crate) resize_policy: ResizePolicy,
+    pub(crate) base_size: Vector2<i32>,
+    pub(crate) render_target: Option<RenderTarget>,
+    pub(crate) present_rect: Rectangle,
+    /// Effects of the signed distance field text in the current batch
+    pub(crate) sdf_uniforms: SdfUniforms,
+    /// String tables `text_key` draws from
+    pub localization: Localization,
+    /// Why text was last skipped, see `take_text_error`
+    pub(crate) text_error: Option<SmolError>,
 }
 
 impl Renderer {
-    pub fn new(render_size: Vector2<i32>) -> Self {
-        let context = GfxContext::new(render_size);
+    pub fn new(settings: &AppSettings) -> Self {
+        let context = GfxContext::new(settings.size);
         let camera = Camera::default();
 
-        Renderer {
+        let mut renderer = Renderer {
             camera,
             context,
-            render_size,
+            render_size: settings.virtual_resolution.unwrap_or(settings.size),
+            window_size: settings.size,
+            virtual_resolution: settings.virtual_resolution,
+            scaling: settings.scaling,
+            resize_policy: settings.resize_policy,
+            base_size: settings.size,
             default_texture: Texture::default(),
+            font_cache: FontCache::new(settings.glyph_cache_size, settings.max_glyph_cache_size),
             ..Default::default()
+        };
+        renderer.set_window_size(settings.size);
+
+        renderer
+    }
+
+    /// Why text was last skipped or drawn a piece at a time, like text that doesn't fit in the
+    /// glyph cache at once. Taking it clears it until it happens again
+    pub fn take_text_error(&mut self) -> Option<SmolError> {
+        self.text_error.take()
+    }
+
+    /// Size of the area cameras project, in world units at a zoom of 1
+    pub fn render_size(&self) -> Vector2<i32> {
+        self.render_size
+    }
+
+    pub fn set_resize_policy(&mut self, resize_policy: ResizePolicy) {
+        self.resize_policy = resize_policy;
+        self.set_window_size(self.window_size);
+    }
+
+    /// Size of the surface draws end up on, the virtual resolution when one is set
+    pub fn target_size(&self) -> Vector2<i32> {
+        if let Some(target) = &self.render_target {
+            target.size
+        } else {
+            self.window_size
         }
     }
 
     pub fn clear(&self, color: Color) {
+        self.apply_viewport();
         self.context.clear_buffer(color);
     }
 
-    pub fn set_shader(&mut self, shader: Shader) {
-        if let Some(bound_shader) = self.bound_shader {
-            if bound_shader != shader {
-                self.flush_batch();
+    /// Flushes anything drawn through the previous camera and makes `camera` the active one,
+    /// call it once per view to render split screen or picture in picture.
+    pub fn set_camera(&mut self, camera: Camera) {
+        self.flush_batch();
+        self.camera = camera;
+        self.apply_viewport();
+    }
+
+    /// Sets the layer following draws belong to, they are skipped by cameras without it.
+    /// Panics if `layer` isn't below `RenderLayers::COUNT`.
+    pub fn set_layer(&mut self, layer: u8) {
+        assert!(
+            layer < RenderLayers::COUNT,
+            "Render layer {} is out of range, there are {} layers",
+            layer,
+            RenderLayers::COUNT
+        );
+        self.layer = layer;
+    }
+
+    /// Maps render pixels, like `Input::get_mouse_pos`, onto the world through the active camera.
+    /// Window pixels only match them without a virtual resolution or resize policy
+    pub fn screen_to_world(&self, screen_pos: Vector2<f32>) -> Vector2<f32> {
+        self.camera.screen_to_world(screen_pos, self.render_size)
+    }
+
+    /// Maps a world position onto render pixels through the active camera
+    pub fn world_to_screen(&self, world_pos: Vector2<f32>) -> Vector2<f32> {
+        self.camera.world_to_screen(world_pos, self.render_size)
+    }
+
+    pub(crate) fn is_layer_visible(&self) -> bool {
+        self.camera.layers.contains(self.layer)
+    }
+
+    /// Viewports are measured in render size units, scale them onto the target's pixels
+    pub(crate) fn apply_viewport(&self) {
+        let target_size = self.target_size();
+        let viewport = self.camera.viewport.to_pixels(self.render_size);
+        let scale_x = target_size.x as f32 / self.render_size.x as f32;
+        let scale_y = target_size.y as f32 / self.render_size.y as f32;
+
+        self.context.set_viewport(
+            Rectangle {
+                x: viewport.x * scale_x,
+                y: viewport.y * scale_y,
+                width: viewport.width * scale_x,
+                height: viewport.height * scale_y,
+            },
+            target_size,
+        );
+    }
+
+    pub(crate) fn set_window_size(&mut self, window_size: Vector2<i32>) {
+        // minimized windows have no size, rendering carries on at the previous one
+        if window_size.x <= 0 || window_size.y <= 0 {
+            return;
+        }
+        self.window_size = window_size;
+
+        if let Some(virtual_resolution) = self.virtual_resolution {
+            let target_size = if self.scaling == ScalingMode::Expand {
+                let scale = fit_scale(virtual_resolution, window_size);
+                Vector::from([
+                    (window_size.x as f32 / scale).ceil() as i32,
+                    (window_size.y as f32 / scale).ceil() as i32,
+                ])
+            } else {
+                virtual_resolution
+            };
+
+            if self.render_target.as_ref().map(|t| t.size) != Some(target_size) {
+                self.render_target = Some(RenderTarget::new(target_size));
             }
+            self.render_size = target_size;
+            self.present_rect = present_rect(self.scaling, target_size, window_size);
+        } else {
+            self.render_size = match self.resize_policy {
+                ResizePolicy::KeepPixelScale => window_size,
+                ResizePolicy::KeepWorldUnits => {
+                    let scale = fit_scale(self.base_size, window_size);
+                    Vector::from([
+                        (window_size.x as f32 / scale).round() as i32,
+                        (window_size.y as f32 / scale).round() as i32,
+                    ])
+                }
+                ResizePolicy::FixedHeight(height) => Vector::from([
+                    (height as f32 * window_size.x as f32 / window_size.y as f32).round() as i32,
+                    height,
+                ]),
+            };
+            self.present_rect = Rectangle {
+                x: 0.,
+                y: 0.,
+                width: window_size.x as f32,
+                height: window_size.y as f32,
+            };
+        }
+
+        self.context.bind_render_target(self.render_target.as_ref());
+        self.apply_viewport();
+    }
+
+    /// Draws the render target scaled into the window
+    fn present(&self) {
+        let target = if let Some(target) = &self.render_target {
+            target
+        } else {
+            return;
+        };
+
+        self.context.bind_render_target(None);
+        self.context.set_viewport(
+            Rectangle {
+                x: 0.,
+                y: 0.,
+                width: self.window_size.x as f32,
+                height: self.window_size.y as f32,
+            },
+            self.window_size,
+        );
+        self.context.clear_buffer(Color::BLACK);
+        self.context
+            .set_viewport(self.present_rect, self.window_size);
+
+        let color = Color::WHITE.normalize();
+        let vertex = |x: f32, y: f32| Vertex {
+            position: Vector::from([x, y, 0., 1.]),
+            color,
+            tex_coords: [(x + 1.) / 2., (y + 1.) / 2.],
+            tex_index: 0,
+        };
+
+        self.context.render(
+            &vec![
+                vertex(1., 1.),
+                vertex(1., -1.),
+                vertex(-1., -1.),
+                vertex(-1., 1.),
+            ],
+            &vec![0, 1, 3, 1, 2, 3],
+            &vec![target.texture],
+            &Matrix4::identity(),
+            None,
+            None,
+        );
+
+        self.context.bind_render_target(Some(target));
+        self.apply_viewport();
+    }
+
+    /// Draws sprites and shapes with `shader` until the end of the frame,
+    /// `Shader::default()` goes back to the built in one
+    pub fn set_shader(&mut self, shader: Shader) {
+        if self.bound_shader.unwrap_or_default() != shader {
+            self.flush_batch();
         }
 
         self.bound_shader = Some(shader);
     }
 
+    /// Draws are submitted in order, so changing what kind of draw is batched (sprites, screen
+    /// or world text) flushes what was batched before it
     pub fn set_batch_id(&mut self, id: &str) {
         if let Some(batch_id) = &self.current_batch_id {
             if batch_id != id {
@@ -86,16 +321,21 @@ impl Renderer {
 
     pub fn render(&mut self) {
         self.flush_batch();
+        self.bound_shader = None;
+        self.present();
     }
 
     pub fn texture(&mut self, transform: Transform, texture: &Texture) {
+        // trimmed textures are anchored as if they had their untrimmed size
+        let anchor = get_anchor_point(transform.anchor, texture.source_size) - texture.offset;
+
         self.push_rect(
             transform.position,
             texture.uv_size,
             transform.scale,
             transform.rotation,
             Color::WHITE.normalize(),
-            transform.anchor,
+            Anchor::Custom(anchor),
             Some(texture),
         )
     }
@@ -112,3 +352,40 @@ pub(crate) fn get_anchor_point(anchor: Anchor, size: Vector2<f32>) -> Vector2<f3
         Anchor::Custom(pos) => pos,
     }
 }
+
+/// Largest scale the virtual resolution can be drawn at inside of the window
+fn fit_scale(size: Vector2<i32>, window_size: Vector2<i32>) -> f32 {
+    f32::min(
+        window_size.x as f32 / size.x as f32,
+        window_size.y as f32 / size.y as f32,
+    )
+}
+
+pub(crate) fn present_rect(
+    scaling: ScalingMode,
+    size: Vector2<i32>,
+    window_size: Vector2<i32>,
+) -> Rectangle {
+    let scale = match scaling {
+        ScalingMode::Stretch => {
+            return Rectangle {
+                x: 0.,
+                y: 0.,
+                width: window_size.x as f32,
+                height: window_size.y as f32,
+            }
+        }
+        ScalingMode::IntegerScale => f32::max(fit_scale(size, window_size).floor(), 1.),
+        ScalingMode::Letterbox | ScalingMode::Expand => fit_scale(size, window_size),
+    };
+
+    let width = size.x as f32 * scale;
+    let height = size.y as f32 * scale;
+
+    Rectangle {
+        x: ((window_size.x as f32 - width) / 2.).floor(),
+        y: ((window_size.y as f32 - height) / 2.).floor(),
+        width,
+        height,
+    }
+}
@@ -0,0 +1,271 @@
+use crate::errors::SmolError;
+use crate::Color;
+
+use super::bitmap_font::TextRun;
+use super::shapes::Rectangle;
+use super::text::TextSettings;
+use super::{Font, FontKind, Renderer};
+
+/// A run of text inside of a rich text block, unset fields fall back to the block's font and
+/// `TextSettings`
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct TextSpan {
+    pub text: String,
+    pub font: Option<Font>,
+    pub color: Option<Color>,
+    pub size: Option<f32>,
+}
+
+impl TextSpan {
+    pub fn new<T: Into<String>>(text: T) -> Self {
+        Self {
+            text: text.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_font(mut self, font: Font) -> Self {
+        self.font = Some(font);
+        self
+    }
+
+    pub fn with_color(mut self, color: Color) -> Self {
+        self.color = Some(color);
+        self
+    }
+
+    pub fn with_size(mut self, size: f32) -> Self {
+        self.size = Some(size);
+        self
+    }
+}
+
+impl From<&str> for TextSpan {
+    fn from(text: &str) -> Self {
+        TextSpan::new(text)
+    }
+}
+
+/// Parses markup like `"Found a [color=red]crit[/color] sword"` into spans.
+///
+/// Supports `[color=name or #hex]`, `[size=pixels]` and `[font=name]` with their closing tags,
+/// nested tags restore the outer style when closed and `[[` writes a literal `[`.
+/// Font names are looked up with `find_font`, usually `|name| app.get_font(name).copied()`.
+pub fn parse_markup<F>(markup: &str, mut find_font: F) -> Result<Vec<TextSpan>, SmolError>
+where
+    F: FnMut(&str) -> Option<Font>,
+{
+    let mut spans = Vec::new();
+    let mut colors: Vec<Color> = Vec::new();
+    let mut sizes: Vec<f32> = Vec::new();
+    let mut fonts: Vec<Font> = Vec::new();
+    let mut text = String::new();
+    let mut chars = markup.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c != '[' {
+            text.push(c);
+            continue;
+        }
+
+        if chars.peek() == Some(&'[') {
+            chars.next();
+            text.push('[');
+            continue;
+        }
+
+        let mut tag = String::new();
+        loop {
+            match chars.next() {
+                Some(']') => break,
+                Some(c) => tag.push(c),
+                None => return Err(SmolError::new(format!("Unclosed markup tag [{}", tag))),
+            }
+        }
+
+        if !text.is_empty() {
+            spans.push(TextSpan {
+                text: std::mem::take(&mut text),
+                font: fonts.last().copied(),
+                color: colors.last().copied(),
+                size: sizes.last().copied(),
+            });
+        }
+
+        let (name, value) = match tag.find('=') {
+            Some(index) => (&tag[..index], Some(tag[index + 1..].trim())),
+            None => (tag.as_str(), None),
+        };
+
+        let closed = match (name.trim(), value) {
+            ("color", Some(value)) => {
+                colors.push(value.parse()?);
+                true
+            }
+            ("size", Some(value)) => {
+                sizes.push(
+                    value
+                        .parse()
+                        .map_err(|_| SmolError::new(format!("Invalid markup size {}", value)))?,
+                );
+                true
+            }
+            ("font", Some(value)) => {
+                let font = find_font(value)
+                    .ok_or_else(|| SmolError::new(format!("Unknown markup font {}", value)))?;
+                fonts.push(font);
+                true
+            }
+            ("/color", None) => colors.pop().is_some(),
+            ("/size", None) => sizes.pop().is_some(),
+            ("/font", None) => fonts.pop().is_some(),
+            _ => return Err(SmolError::new(format!("Unknown markup tag [{}]", tag))),
+        };
+
+        if !closed {
+            return Err(SmolError::new(format!(
+                "Markup tag [{}] has no opening tag",
+                tag
+            )));
+        }
+    }
+
+    if !text.is_empty() {
+        spans.push(TextSpan {
+            text,
+            font: fonts.last().copied(),
+            color: colors.last().copied(),
+            size: sizes.last().copied(),
+        });
+    }
+
+    Ok(spans)
+}
+
+impl Renderer {
+    /// Lays out spans with mixed fonts, colours and sizes as one block of text
+    pub fn rich_text(&mut self, font: &Font, spans: &[TextSpan], settings: &TextSettings) {
+        if !self.is_layer_visible() || !self.font_cache.is_alive(font) {
+            return;
+        }
+
+        match font.kind {
+            FontKind::Vector => {
+                let section = self.font_cache.apply_fallbacks(settings.rich_section(
+                    font,
+                    spans,
+                    &self.font_cache,
+                ));
+                self.queue_text(section, settings)
+            }
+            FontKind::Bitmap => {
+                let runs = bitmap_runs(spans, settings);
+                self.bitmap_text(font, &runs, settings);
+            }
+            FontKind::Sdf => {
+                let section = self.font_cache.apply_fallbacks(settings.rich_section(
+                    font,
+                    spans,
+                    &self.font_cache,
+                ));
+                self.sdf_text(section, settings)
+            }
+        }
+    }
+
+    pub fn measure_rich_text(
+        &mut self,
+        font: &Font,
+        spans: &[TextSpan],
+        settings: &TextSettings,
+    ) -> Rectangle {
+        if font.kind == FontKind::Bitmap {
+            return self.measure_bitmap_text(font, &bitmap_runs(spans, settings), settings);
+        }
+
+        let section =
+            self.font_cache
+                .apply_fallbacks(settings.rich_section(font, spans, &self.font_cache));
+        self.measure_section(section, settings)
+    }
+}
+
+/// Bitmap fonts only take the colour of each span
+fn bitmap_runs<'a>(spans: &'a [TextSpan], settings: &TextSettings) -> Vec<TextRun<'a>> {
+    spans
+        .iter()
+        .map(|span| {
+            let color = span.color.unwrap_or(settings.color);
+            (span.text.as_str(), color.normalize())
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn no_fonts(_: &str) -> Option<Font> {
+        None
+    }
+
+    #[test]
+    fn plain_text_is_one_span() {
+        assert_eq!(
+            parse_markup("Hello there", no_fonts).unwrap(),
+            [TextSpan::new("Hello there")]
+        );
+        assert!(parse_markup("", no_fonts).unwrap().is_empty());
+    }
+
+    #[test]
+    fn nested_tags_restore_the_outer_style() {
+        let spans =
+            parse_markup("a [color=red]b [size=20]c[/size] d[/color] [[e]", no_fonts).unwrap();
+
+        assert_eq!(
+            spans,
+            [
+                TextSpan::new("a "),
+                TextSpan::new("b ").with_color(Color::RED),
+                TextSpan::new("c").with_color(Color::RED).with_size(20.),
+                TextSpan::new(" d").with_color(Color::RED),
+                TextSpan::new(" [e]"),
+            ]
+        );
+    }
+
+    #[test]
+    fn hex_colors_and_fonts() {
+        let bold = Font {
+            id: 3,
+            size: 12.,
+            ..Default::default()
+        };
+        let spans = parse_markup("[font=bold][color=#00ff0080]x[/color][/font]", |name| {
+            Some(bold).filter(|_| name == "bold")
+        })
+        .unwrap();
+
+        assert_eq!(
+            spans,
+            [TextSpan::new("x")
+                .with_font(bold)
+                .with_color(Color(0, 255, 0, 128. / 255.))]
+        );
+    }
+
+    #[test]
+    fn invalid_markup_is_an_error() {
+        for markup in [
+            "[color=red",
+            "[/color]",
+            "[bold]x[/bold]",
+            "[size=big]x",
+            "[color=nope]x",
+            "[font=missing]x",
+        ] {
+            assert!(parse_markup(markup, no_fonts).is_err(), "{}", markup);
+        }
+    }
+}
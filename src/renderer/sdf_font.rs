@@ -0,0 +1,327 @@
+use ab_glyph::{point, Font as _, FontArc, GlyphId};
+use glyph_brush::{GlyphCruncher, Section, SectionGlyph};
+use hashbrown::HashMap;
+use nalgebra::{Matrix4, Vector, Vector2};
+
+use crate::errors::SmolError;
+use crate::gfx::{GfxContext, SdfUniforms};
+
+use super::bitmap_font::clip_rect;
+use super::shapes::Rectangle;
+use super::text::{text_transform_matrix, TextOverflow, TextSettings, TextSpace};
+use super::text_effects::{glyph_color, transform_rect, SectionChars};
+use super::{Renderer, Texture};
+
+/// Pixel size glyph distance fields are generated at
+pub const SDF_FONT_SIZE: f32 = 32.;
+/// Pixels around each glyph the distance field reaches, outlines, glows and shadows wider than
+/// this at `SDF_FONT_SIZE` get cut off
+pub const SDF_SPREAD: u32 = 6;
+/// Pixels a shadow can be offset by at `SDF_FONT_SIZE`, glyphs are padded by this much so the
+/// shadow doesn't reach into neighbouring glyphs. Larger offsets are capped
+pub const SDF_MAX_SHADOW_OFFSET: u32 = 8;
+const SDF_ATLAS_SIZE: u32 = 2048;
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct SdfGlyph {
+    pub texture: Texture,
+    /// Offset of the image from the glyph's position on the baseline
+    pub offset: Vector2<f32>,
+}
+
+/// Distance fields of the glyphs of one font, generated the first time each glyph is drawn.
+/// Glyphs are packed into atlas pages, a new page is added when the last one is full
+#[derive(Debug)]
+pub(crate) struct SdfFont {
+    pages: Vec<Texture>,
+    /// Index of the page glyphs are being added to
+    page: usize,
+    /// `None` for glyphs without an outline, like spaces
+    glyphs: HashMap<GlyphId, Option<SdfGlyph>>,
+    shelf_x: u32,
+    shelf_y: u32,
+    shelf_height: u32,
+}
+
+impl Drop for SdfFont {
+    fn drop(&mut self) {
+        for page in &self.pages {
+            GfxContext::delete_texture(page.id);
+        }
+    }
+}
+
+impl SdfFont {
+    pub fn new() -> Self {
+        SdfFont {
+            pages: vec![new_page()],
+            page: 0,
+            glyphs: HashMap::new(),
+            shelf_x: 0,
+            shelf_y: 0,
+            shelf_height: 0,
+        }
+    }
+
+    /// Forgets every generated glyph, they're generated again into the same pages when drawn
+    pub fn clear(&mut self) {
+        self.glyphs.clear();
+        self.page = 0;
+        self.shelf_x = 0;
+        self.shelf_y = 0;
+        self.shelf_height = 0;
+    }
+
+    pub fn glyph(&mut self, font: &FontArc, id: GlyphId) -> Result<Option<SdfGlyph>, SmolError> {
+        if let Some(glyph) = self.glyphs.get(&id) {
+            return Ok(*glyph);
+        }
+
+        let glyph = match generate_distance_field(font, id) {
+            Some((data, size, offset)) => {
+                let (page, position) = self.allocate(size).ok_or_else(|| {
+                    SmolError::new(format!(
+                        "Glyph {} is too big for an SDF atlas page of {}x{}",
+                        id.0, SDF_ATLAS_SIZE, SDF_ATLAS_SIZE
+                    ))
+                })?;
+                GfxContext::update_font_texture(page.id, position, size, &data);
+
+                // the outer padding is only there for shadows to sample, it's never drawn
+                let padding = SDF_MAX_SHADOW_OFFSET as f32;
+                Some(SdfGlyph {
+                    texture: page.region(
+                        Vector::from([position.0 as f32 + padding, position.1 as f32 + padding]),
+                        Vector::from([size.0 as f32 - padding * 2., size.1 as f32 - padding * 2.]),
+                    ),
+                    offset: offset + Vector::from([padding, padding]),
+                })
+            }
+            None => None,
+        };
+        self.glyphs.insert(id, glyph);
+
+        Ok(glyph)
+    }
+
+    /// Finds space in the pages, shelves of glyphs are filled left to right
+    fn allocate(&mut self, (width, height): (u32, u32)) -> Option<(Texture, (u32, u32))> {
+        if width > SDF_ATLAS_SIZE || height > SDF_ATLAS_SIZE {
+            return None;
+        }
+
+        if self.shelf_x + width > SDF_ATLAS_SIZE {
+            self.shelf_x = 0;
+            self.shelf_y += self.shelf_height + 1;
+            self.shelf_height = 0;
+        }
+        if self.shelf_y + height > SDF_ATLAS_SIZE {
+            self.page += 1;
+            if self.page == self.pages.len() {
+                self.pages.push(new_page());
+            }
+            self.shelf_x = 0;
+            self.shelf_y = 0;
+            self.shelf_height = 0;
+        }
+
+        let position = (self.shelf_x, self.shelf_y);
+        self.shelf_x += width + 1;
+        self.shelf_height = self.shelf_height.max(height);
+
+        Some((self.pages[self.page], position))
+    }
+}
+
+fn new_page() -> Texture {
+    let texture_id = GfxContext::generate_font_texture((SDF_ATLAS_SIZE, SDF_ATLAS_SIZE));
+    let size = Vector::from([SDF_ATLAS_SIZE as f32, SDF_ATLAS_SIZE as f32]);
+
+    Texture::new(texture_id, size, Vector2::default(), size)
+}
+
+/// Pixels, size and offset from the glyph's position of a distance field image, padded by twice
+/// `SDF_MAX_SHADOW_OFFSET` so shadows sampled from the edge of the drawn part stay in the glyph
+type DistanceField = (Vec<u8>, (u32, u32), Vector2<f32>);
+
+/// Distance to the glyph's edge for every pixel around it at `SDF_FONT_SIZE`, 0.5 on the edge
+/// and higher inside
+fn generate_distance_field(font: &FontArc, id: GlyphId) -> Option<DistanceField> {
+    let outlined = font.outline_glyph(id.with_scale_and_position(SDF_FONT_SIZE, point(0., 0.)))?;
+    let bounds = outlined.px_bounds();
+    let spread = SDF_SPREAD as i32;
+    let width = bounds.width() as i32 + spread * 2;
+    let height = bounds.height() as i32 + spread * 2;
+
+    let mut coverage = vec![0f32; (width * height) as usize];
+    outlined.draw(|x, y, c| {
+        let index = (y as i32 + spread) * width + x as i32 + spread;
+        coverage[index as usize] = c;
+    });
+    let is_inside = |x: i32, y: i32| {
+        x >= 0 && y >= 0 && x < width && y < height && coverage[(y * width + x) as usize] >= 0.5
+    };
+
+    let mut data = vec![0; coverage.len()];
+    for y in 0..height {
+        for x in 0..width {
+            let c = coverage[(y * width + x) as usize];
+            let distance = if c > 0. && c < 1. {
+                // partly covered pixels sit on the edge
+                c - 0.5
+            } else {
+                let inside = c >= 0.5;
+                let mut nearest = (2 * spread * spread) as f32;
+                for dy in -spread..=spread {
+                    for dx in -spread..=spread {
+                        if is_inside(x + dx, y + dy) != inside {
+                            nearest = nearest.min((dx * dx + dy * dy) as f32);
+                        }
+                    }
+                }
+
+                let distance = nearest.sqrt() - 0.5;
+                if inside {
+                    distance
+                } else {
+                    -distance
+                }
+            };
+
+            let value = 0.5 + distance / (2. * SDF_SPREAD as f32);
+            data[(y * width + x) as usize] = (value.clamp(0., 1.) * 255.) as u8;
+        }
+    }
+
+    let padding = SDF_MAX_SHADOW_OFFSET * 2;
+    let (padded_width, padded_height) = (width as u32 + padding * 2, height as u32 + padding * 2);
+    let mut padded = vec![0; (padded_width * padded_height) as usize];
+    for (y, row) in data.chunks(width as usize).enumerate() {
+        let start = ((y as u32 + padding) * padded_width + padding) as usize;
+        padded[start..start + row.len()].copy_from_slice(row);
+    }
+
+    let offset = Vector::from([
+        bounds.min.x - (SDF_SPREAD + padding) as f32,
+        bounds.min.y - (SDF_SPREAD + padding) as f32,
+    ]);
+
+    Some((padded, (padded_width, padded_height), offset))
+}
+
+fn sdf_uniforms(settings: &TextSettings, scale: f32) -> SdfUniforms {
+    let mut uniforms = SdfUniforms {
+        distance_scale: 2. * SDF_SPREAD as f32 * scale,
+        ..Default::default()
+    };
+
+    if let Some(outline) = settings.outline {
+        uniforms.outline_color = outline.color.normalize();
+        uniforms.outline_width = outline.width;
+    }
+    if let Some(glow) = settings.glow {
+        uniforms.glow_color = glow.color.normalize();
+        uniforms.glow_radius = glow.radius;
+    }
+    if let Some(shadow) = settings.shadow {
+        uniforms.shadow_color = shadow.color.normalize();
+        let max_offset = SDF_MAX_SHADOW_OFFSET as f32;
+        uniforms.shadow_offset = [
+            (shadow.offset.x / scale).clamp(-max_offset, max_offset) / SDF_ATLAS_SIZE as f32,
+            (shadow.offset.y / scale).clamp(-max_offset, max_offset) / SDF_ATLAS_SIZE as f32,
+        ];
+        uniforms.shadow_softness = shadow.softness;
+    }
+
+    uniforms
+}
+
+impl Renderer {
+    /// Draws text of SDF fonts, glyph_brush still lays the text out but the glyphs come from
+    /// the fonts' distance field atlases
+    pub(crate) fn sdf_text(&mut self, section: Section, settings: &TextSettings) {
+        let chars = SectionChars::new(&section);
+        let colors: Vec<[f32; 4]> = section.text.iter().map(|text| text.extra.color).collect();
+        let model = match settings.transform {
+            Some(transform) => {
+                let bounds = self.measure_section(section.clone(), settings);
+                text_transform_matrix(bounds, &transform)
+            }
+            None => Matrix4::identity(),
+        };
+        let clip = settings
+            .bounds
+            .filter(|_| settings.overflow != TextOverflow::Visible);
+
+        let brush = &mut self.font_cache.brush;
+        let glyphs: Vec<SectionGlyph> = brush
+            .glyphs_custom_layout(section, &settings.layout())
+            .cloned()
+            .collect();
+        let fonts = brush.fonts().to_vec();
+
+        let mut quads = Vec::new();
+        for section_glyph in glyphs {
+            let transform = match settings.transform_glyph(&chars.glyph(&section_glyph)) {
+                Some(transform) => transform,
+                None => continue,
+            };
+            let font_id = section_glyph.font_id.0;
+            let sdf_font = match self.font_cache.sdf_fonts.get_mut(&font_id) {
+                Some(sdf_font) => sdf_font,
+                None => continue,
+            };
+            let glyph = match sdf_font.glyph(&fonts[font_id], section_glyph.glyph.id) {
+                Ok(Some(glyph)) => glyph,
+                Ok(None) => continue,
+                Err(error) => {
+                    self.text_error = Some(error);
+                    continue;
+                }
+            };
+
+            let glyph_scale = section_glyph.glyph.scale.y / SDF_FONT_SIZE;
+            let position = section_glyph.glyph.position;
+            let rect = Rectangle {
+                x: position.x + glyph.offset.x * glyph_scale,
+                y: position.y + glyph.offset.y * glyph_scale,
+                width: glyph.texture.uv_size.x * glyph_scale,
+                height: glyph.texture.uv_size.y * glyph_scale,
+            };
+            let rect = transform_rect(rect, &transform);
+            if let Some((rect, part)) = clip_rect(rect, clip) {
+                let color = glyph_color(colors[section_glyph.section_index], &transform);
+                // effects are sized in pixels of the text, so they follow the size of each span
+                let uniforms = sdf_uniforms(settings, glyph_scale);
+                quads.push((rect, part, color, glyph.texture, uniforms));
+            }
+        }
+
+        match settings.space {
+            TextSpace::Screen => self.set_batch_id("sdf_text"),
+            TextSpace::World => self.set_batch_id("sdf_world_text"),
+        }
+
+        for (rect, part, color, texture, uniforms) in quads {
+            self.set_sdf_uniforms(uniforms);
+            let quad_model = model
+                * Matrix4::new_translation(&Vector::from([rect.x, rect.y, 0.]))
+                * Matrix4::new_nonuniform_scaling(&Vector::from([rect.width, rect.height, 1.]));
+            self.push_quad(
+                &quad_model,
+                texture.get_sub_tex_coords(part),
+                color,
+                Some(&texture),
+            );
+        }
+    }
+
+    /// Effects are uniforms, so text with different effects or sizes can't share a draw
+    fn set_sdf_uniforms(&mut self, uniforms: SdfUniforms) {
+        if self.sdf_uniforms != uniforms {
+            self.flush_batch();
+        }
+
+        self.sdf_uniforms = uniforms;
+    }
+}
@@ -1,4 +1,16 @@
+/// Vertex shader sprites and shapes are drawn with, custom fragment shaders can be paired with it
+/// through `App::load_shader`
+pub const DEFAULT_VERTEX_SHADER: &str = include_str!("../shaders/opengl/2d.vs");
+
+/// Shader program used by `Renderer::set_shader`, custom vertex shaders need the same inputs as
+/// `DEFAULT_VERTEX_SHADER`
 #[derive(Debug, Default, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
 pub struct Shader {
-    id: u32,
+    pub(crate) id: u32,
+}
+
+impl Shader {
+    pub(crate) fn new(id: u32) -> Self {
+        Shader { id }
+    }
 }
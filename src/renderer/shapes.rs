@@ -26,6 +26,13 @@ impl Renderer {
     }
 
     pub fn rectangle(&mut self, rect: Rectangle, color: Color) {
+        if !self.is_layer_visible() {
+            return;
+        }
+
+        self.check_batch_overflow();
+        self.set_batch_id("rect");
+
         let verticies_amount: i32 = self.verticies.len() as _;
 
         let mut new_indicies: Vec<i32> = vec![
@@ -114,6 +121,13 @@ impl Renderer {
         pos2: Vector2<f32>,
         color: Color,
     ) {
+        if !self.is_layer_visible() {
+            return;
+        }
+
+        self.check_batch_overflow();
+        self.set_batch_id("rect");
+
         let verticies_amount: i32 = self.verticies.len() as _;
 
         let mut new_indicies: Vec<i32> = vec![
@@ -155,6 +169,13 @@ impl Renderer {
     }
 
     pub fn line(&mut self, from: Vector2<f32>, to: Vector2<f32>, thickness: f32, color: Color) {
+        if !self.is_layer_visible() {
+            return;
+        }
+
+        self.check_batch_overflow();
+        self.set_batch_id("rect");
+
         let normal = (to - from).normalize();
         let perp = Vector::from([normal.y, -normal.x]);
         let pos0 = from + perp * thickness * 0.5;
@@ -1,18 +1,495 @@
-use ab_glyph::{point, Rect};
-use glyph_brush::{BrushAction, HorizontalAlign, Layout, Section, Text, VerticalAlign};
-use nalgebra::Vector2;
+use std::ops::Range;
+
+use ab_glyph::{point, Font as _, FontArc, Rect, ScaleFont};
+use glyph_brush::{
+    BrushAction, BrushError, BuiltInLineBreaker, Extra, FontId, GlyphBrush, GlyphBrushBuilder,
+    GlyphCruncher, HorizontalAlign, Layout, OwnedSection, Section, SectionGlyph, Text,
+    VerticalAlign,
+};
+use hashbrown::HashMap;
+use nalgebra::{Matrix4, Point3, Vector2};
 
 use crate::errors::SmolError;
-use crate::Color;
+use crate::gfx::GfxContext;
+use crate::{Color, Transform};
+
+use super::bitmap_font::BitmapFont;
+use super::rich_text::TextSpan;
+use super::sdf_font::SdfFont;
+use super::shapes::Rectangle;
+use super::text_effects::GlyphTransformFn;
+pub use super::text_layout::TextOverflow;
+use super::text_layout::{split_lines, TextLayout};
+use super::{get_anchor_point, Font, FontKind, Renderer, Texture};
 
-use super::{Font, Renderer};
+/// Default width and height of the glyph cache texture
+pub const DEFAULT_GLYPH_CACHE_SIZE: u32 = 256;
+/// Default width and height the glyph cache texture stops growing at
+pub const DEFAULT_MAX_GLYPH_CACHE_SIZE: u32 = 4096;
 
 pub type TextAlignment = HorizontalAlign;
+pub type TextVerticalAlignment = VerticalAlign;
+
+/// Where lines are broken when they get wider than the bounds
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum TextWrap {
+    /// Breaks between words, long words still get broken up
+    #[default]
+    Word,
+    /// Breaks between any characters
+    Char,
+    /// Only breaks on new lines
+    None,
+}
+
+/// Every loaded font shares one glyph brush and cache texture so a section can mix them
+pub(crate) struct FontCache {
+    pub brush: GlyphBrush<[f32; 13]>,
+    pub texture: Texture,
+    pub bitmap_fonts: Vec<BitmapFont>,
+    /// Distance field atlases of SDF fonts, by their index in the brush
+    pub sdf_fonts: HashMap<usize, SdfFont>,
+    /// Brush indices of the fonts tried for characters a font doesn't have, starting with the
+    /// font itself
+    pub fallbacks: HashMap<usize, Vec<usize>>,
+    /// Size the cache texture stops growing at
+    pub max_texture_size: Vector2<u32>,
+    /// Brush indices of unloaded vector and SDF fonts, the next fonts added take them over
+    pub free_font_ids: Vec<usize>,
+    /// Times each brush index was freed
+    pub font_generations: HashMap<usize, u32>,
+    /// Indices of unloaded bitmap fonts, the next bitmap fonts added take them over
+    pub free_bitmap_ids: Vec<usize>,
+    /// Times each bitmap font index was freed
+    pub bitmap_generations: HashMap<usize, u32>,
+    /// Text queued in the brush since it was last drawn, see `Renderer::render_all_text_queue`
+    pub queued: Vec<QueuedText>,
+}
+
+/// Text queued in the glyph brush
+pub(crate) enum QueuedText {
+    Section(OwnedSection, TextLayout),
+    /// Glyphs already laid out, with their extras and clip bounds
+    Positioned(Vec<SectionGlyph>, Vec<Extra>, Rect),
+}
+
+/// Placeholder for the brush index of an unloaded font. glyph_brush can't remove fonts since
+/// they're found by index, so the slot keeps this until another font takes it over and the
+/// unloaded font's data is freed instead of staying in the brush.
+///
+/// It's the smallest OpenType font ab_glyph reads: only the `head`, `hhea` and `maxp` tables,
+/// laid out as in the spec's table directory with zeroed checksums, which aren't verified.
+/// There's no `cmap`, so every character maps to glyph 0, and no `glyf` or `hmtx`, so that
+/// glyph has no outline or advance. It's still one em high, scaled metrics divide by the
+/// height. Fallbacks skip it since they look for glyphs other than 0
+fn empty_font() -> FontArc {
+    let mut head = vec![0; 54];
+    head[0..4].copy_from_slice(&0x0001_0000u32.to_be_bytes()); // version
+    head[12..16].copy_from_slice(&0x5F0F_3CF5u32.to_be_bytes()); // magic number
+    head[18..20].copy_from_slice(&1000u16.to_be_bytes()); // units per em
+
+    let mut hhea = vec![0; 36];
+    hhea[0..4].copy_from_slice(&0x0001_0000u32.to_be_bytes()); // version
+    hhea[4..6].copy_from_slice(&800i16.to_be_bytes()); // ascender
+    hhea[6..8].copy_from_slice(&(-200i16).to_be_bytes()); // descender, one em high in all
+
+    let mut maxp = vec![0; 6];
+    maxp[0..4].copy_from_slice(&0x0000_5000u32.to_be_bytes()); // version
+    maxp[4..6].copy_from_slice(&1u16.to_be_bytes()); // glyph count
+
+    let tables = [(b"head", head), (b"hhea", hhea), (b"maxp", maxp)];
+    let mut data = Vec::new();
+    data.extend_from_slice(&0x0001_0000u32.to_be_bytes());
+    data.extend_from_slice(&(tables.len() as u16).to_be_bytes());
+    // binary search hints, they aren't needed to read the tables
+    data.extend_from_slice(&[0; 6]);
+    let mut offset = 12 + 16 * tables.len();
+    for (tag, table) in &tables {
+        data.extend_from_slice(*tag);
+        // checksum
+        data.extend_from_slice(&[0; 4]);
+        data.extend_from_slice(&(offset as u32).to_be_bytes());
+        data.extend_from_slice(&(table.len() as u32).to_be_bytes());
+        offset += table.len().div_ceil(4) * 4;
+    }
+    for (_, table) in &tables {
+        data.extend_from_slice(table);
+        data.resize(data.len().div_ceil(4) * 4, 0);
+    }
+
+    FontArc::try_from_vec(data).expect("empty font is valid")
+}
+
+impl Default for FontCache {
+    fn default() -> Self {
+        FontCache::new(
+            Vector2::from([DEFAULT_GLYPH_CACHE_SIZE, DEFAULT_GLYPH_CACHE_SIZE]),
+            Vector2::from([DEFAULT_MAX_GLYPH_CACHE_SIZE, DEFAULT_MAX_GLYPH_CACHE_SIZE]),
+        )
+    }
+}
+
+impl Drop for FontCache {
+    fn drop(&mut self) {
+        if self.texture.id != 0 {
+            GfxContext::delete_texture(self.texture.id);
+        }
+    }
+}
+
+impl FontCache {
+    pub fn new(texture_size: Vector2<u32>, max_texture_size: Vector2<u32>) -> Self {
+        FontCache {
+            brush: GlyphBrushBuilder::using_fonts(Vec::<FontArc>::new())
+                .initial_cache_size((texture_size.x, texture_size.y))
+                .build(),
+            texture: Default::default(),
+            bitmap_fonts: Vec::new(),
+            sdf_fonts: HashMap::new(),
+            fallbacks: HashMap::new(),
+            max_texture_size,
+            free_font_ids: Vec::new(),
+            font_generations: HashMap::new(),
+            free_bitmap_ids: Vec::new(),
+            bitmap_generations: HashMap::new(),
+            queued: Vec::new(),
+        }
+    }
+
+    /// Replaces the cache texture with one of the suggested size, capped by
+    /// `max_texture_size`. The brush forgets its cached glyphs and uploads them again
+    fn grow_texture(&mut self, suggested: (u32, u32)) -> Result<(), SmolError> {
+        let current = self.brush.texture_dimensions();
+        let dimensions = (
+            suggested.0.min(self.max_texture_size.x),
+            suggested.1.min(self.max_texture_size.y),
+        );
+        if dimensions.0 <= current.0 && dimensions.1 <= current.1 {
+            return Err(SmolError::new(format!(
+                "Text queued since the last draw doesn't fit in the maximum glyph cache size of \
+                 {}x{}, it's drawn a piece at a time and pieces that don't fit on their own are \
+                 skipped",
+                current.0, current.1
+            )));
+        }
+
+        GfxContext::delete_texture(self.texture.id);
+        let texture_id = GfxContext::generate_font_texture(dimensions);
+        let size = Vector2::from([dimensions.0 as f32, dimensions.1 as f32]);
+        self.texture = Texture::new(texture_id, size, Vector2::default(), size);
+        self.brush.resize_texture(dimensions.0, dimensions.1);
+
+        Ok(())
+    }
+
+    pub fn queue(&mut self, text: QueuedText) {
+        self.queue_in_brush(&text);
+        self.queued.push(text);
+    }
+
+    fn queue_in_brush(&mut self, text: &QueuedText) {
+        match text {
+            QueuedText::Section(section, layout) => self.brush.queue_custom_layout(section, layout),
+            QueuedText::Positioned(glyphs, extras, bounds) => {
+                self.brush
+                    .queue_pre_positioned(glyphs.clone(), extras.clone(), *bounds)
+            }
+        }
+    }
+
+    /// Forgets the text queued in the brush, along with the glyphs it cached
+    fn clear_queue(&mut self) {
+        self.brush = self.brush.to_builder().build();
+    }
+
+    /// Uploads the glyphs of the queued text, growing the cache texture until they fit
+    fn process_queued(&mut self) -> Result<BrushAction<[f32; 13]>, SmolError> {
+        loop {
+            let texture_id = self.texture.id;
+            let result = self.brush.process_queued(
+                |rect, data| {
+                    GfxContext::update_font_texture(
+                        texture_id,
+                        (rect.min[0], rect.min[1]),
+                        (rect.width(), rect.height()),
+                        data,
+                    )
+                },
+                to_vertex,
+            );
+
+            match result {
+                Ok(brush_action) => return Ok(brush_action),
+                Err(BrushError::TextureTooSmall { suggested }) => self.grow_texture(suggested)?,
+            }
+        }
+    }
+
+    /// Font the next vector or SDF font added to the brush becomes
+    pub fn next_brush_font(&self, kind: FontKind, size: f32) -> Font {
+        let id = match self.free_font_ids.last() {
+            Some(id) => *id,
+            None => self.brush.fonts().len(),
+        };
+
+        Font {
+            id,
+            kind,
+            size,
+            generation: self.font_generation(kind, id),
+        }
+    }
+
+    /// Font the next bitmap font added becomes
+    pub fn next_bitmap_font(&self, size: f32) -> Font {
+        let id = match self.free_bitmap_ids.last() {
+            Some(id) => *id,
+            None => self.bitmap_fonts.len(),
+        };
+
+        Font {
+            id,
+            kind: FontKind::Bitmap,
+            size,
+            generation: self.font_generation(FontKind::Bitmap, id),
+        }
+    }
+
+    pub fn add_bitmap_font(&mut self, font: BitmapFont) -> usize {
+        match self.free_bitmap_ids.pop() {
+            Some(id) => {
+                self.bitmap_fonts[id] = font;
+                id
+            }
+            None => {
+                self.bitmap_fonts.push(font);
+                self.bitmap_fonts.len() - 1
+            }
+        }
+    }
+
+    pub fn add_font(&mut self, font: FontArc) -> FontId {
+        if self.texture.id == 0 {
+            let dimensions = self.brush.texture_dimensions();
+            let texture_id = GfxContext::generate_font_texture(dimensions);
+            let size = Vector2::from([dimensions.0 as f32, dimensions.1 as f32]);
+            self.texture = Texture::new(texture_id, size, Vector2::default(), size);
+        }
+
+        FontId(self.add_to_brush(font))
+    }
+
+    /// Adds a font without the glyph brush texture, the brush only lays it out
+    pub fn add_sdf_font(&mut self, font: FontArc) -> FontId {
+        let id = self.add_to_brush(font);
+        self.sdf_fonts.insert(id, SdfFont::new());
+
+        FontId(id)
+    }
+
+    fn add_to_brush(&mut self, font: FontArc) -> usize {
+        match self.free_font_ids.pop() {
+            Some(id) => {
+                self.replace_font(id, font);
+                id
+            }
+            None => self.brush.add_font(font).0,
+        }
+    }
+
+    /// Bitmap fonts are counted apart since their indices aren't brush indices
+    fn font_generation(&self, kind: FontKind, id: usize) -> u32 {
+        let generations = match kind {
+            FontKind::Bitmap => &self.bitmap_generations,
+            FontKind::Vector | FontKind::Sdf => &self.font_generations,
+        };
+
+        generations.get(&id).copied().unwrap_or_default()
+    }
+
+    /// Whether a font can still be drawn or measured, copies of an unloaded font are skipped
+    /// instead of using the font that took over its index
+    pub fn is_alive(&self, font: &Font) -> bool {
+        font.generation == self.font_generation(font.kind, font.id)
+    }
+
+    /// Swaps the font at brush index `id` for `font`, glyphs cached from the old one are dropped
+    pub fn replace_font(&mut self, id: usize, font: FontArc) {
+        self.brush = self
+            .brush
+            .to_builder()
+            .replace_fonts(|mut fonts| {
+                fonts[id] = font;
+                fonts
+            })
+            .build();
+
+        if let Some(sdf_font) = self.sdf_fonts.get_mut(&id) {
+            sdf_font.clear();
+        }
+    }
+
+    /// Frees a font, bitmap and SDF fonts delete their textures. Fonts are found by their index,
+    /// so they leave an empty font in their place that the next font of the same kind added
+    /// replaces
+    pub fn unload_font(&mut self, font: Font) {
+        if font.kind == FontKind::Bitmap {
+            self.bitmap_fonts[font.id] = BitmapFont::default();
+            *self.bitmap_generations.entry(font.id).or_insert(0) += 1;
+            self.free_bitmap_ids.push(font.id);
+            return;
+        }
+
+        self.sdf_fonts.remove(&font.id);
+        self.replace_font(font.id, empty_font());
+        *self.font_generations.entry(font.id).or_insert(0) += 1;
+        self.free_font_ids.push(font.id);
+
+        self.fallbacks.remove(&font.id);
+        for family in self.fallbacks.values_mut() {
+            family.retain(|id| *id != font.id);
+        }
+    }
+
+    /// Splits each text into runs drawn by the first font of its family that has the characters
+    pub fn apply_fallbacks<'a>(&self, section: Section<'a>) -> Section<'a> {
+        if self.fallbacks.is_empty() {
+            return section;
+        }
+
+        let fonts = self.brush.fonts();
+        let mut texts = Vec::with_capacity(section.text.len());
+        for text in &section.text {
+            let family = match self.fallbacks.get(&text.font_id.0) {
+                Some(family) => family,
+                None => {
+                    texts.push(*text);
+                    continue;
+                }
+            };
+
+            let mut run_start = 0;
+            let mut run_font = text.font_id;
+            for (index, c) in text.text.char_indices() {
+                // whitespace stays in the current run so words aren't split up
+                if c.is_whitespace() {
+                    continue;
+                }
+
+                let font_id = family
+                    .iter()
+                    .copied()
+                    .find(|id| fonts[*id].glyph_id(c).0 != 0)
+                    .map_or(text.font_id, FontId);
+                if font_id != run_font {
+                    if index > run_start {
+                        texts.push(Text {
+                            text: &text.text[run_start..index],
+                            font_id: run_font,
+                            ..*text
+                        });
+                    }
+                    run_start = index;
+                    run_font = font_id;
+                }
+            }
+            texts.push(Text {
+                text: &text.text[run_start..],
+                font_id: run_font,
+                ..*text
+            });
+        }
+
+        Section {
+            text: texts,
+            ..section
+        }
+    }
+}
+
+/// Font tried first for each character, then each of its fallbacks in order
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FontFamily {
+    pub fonts: Vec<Font>,
+}
+
+impl FontFamily {
+    pub fn new(font: Font) -> Self {
+        FontFamily { fonts: vec![font] }
+    }
+
+    pub fn with_fallback(mut self, font: Font) -> Self {
+        self.fonts.push(font);
+        self
+    }
+}
+
+/// What the position of text is measured in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TextSpace {
+    /// Pixels of the render size, ignoring the camera, for HUDs and menus
+    #[default]
+    Screen,
+    /// World units, moving with the camera
+    World,
+}
+
+/// Line around the edge of each glyph, `width` is in pixels of the text
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextOutline {
+    pub width: f32,
+    pub color: Color,
+}
+
+/// Light fading out over `radius` pixels around the text
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextGlow {
+    pub radius: f32,
+    pub color: Color,
+}
+
+/// Copy of the text drawn behind it, `softness` blurs its edge over that many pixels.
+/// SDF fonts cap the offset at a quarter of the text's size
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextShadow {
+    pub offset: Vector2<f32>,
+    pub softness: f32,
+    pub color: Color,
+}
 
 pub struct TextSettings {
     pub color: Color,
     pub alignment: TextAlignment,
     pub position: Vector2<f32>,
+    /// Pixel size, `None` uses the size the font was loaded with
+    pub size: Option<f32>,
+    /// Multiplier of the font's line spacing
+    pub line_height: f32,
+    /// Extra pixels between each character
+    pub letter_spacing: f32,
+    /// Wraps lines longer than this many pixels, ignored when `bounds` is set
+    pub max_width: Option<f32>,
+    /// Area the text is wrapped, aligned and clipped in, `position` is ignored when set
+    pub bounds: Option<Rectangle>,
+    pub wrap: TextWrap,
+    pub vertical_alignment: TextVerticalAlignment,
+    pub overflow: TextOverflow,
+    pub max_lines: Option<usize>,
+    pub space: TextSpace,
+    /// Moves, rotates and scales the laid out text, the rotation and scale happen around
+    /// `anchor` of the text's bounds and `position` is added to the text's own
+    pub transform: Option<Transform>,
+    /// Vector and bitmap fonts draw it as copies of the text around it, which only looks right
+    /// a few pixels wide. SDF fonts draw any width up to their spread, see `App::load_sdf_font`
+    pub outline: Option<TextOutline>,
+    /// Only drawn by SDF fonts
+    pub glow: Option<TextGlow>,
+    /// Only SDF fonts soften its edge
+    pub shadow: Option<TextShadow>,
+    /// Typewriter reveal, only this many characters from the start are drawn
+    pub visible_chars: Option<usize>,
+    /// Moves, scales and recolors each character, like `GlyphTransform::wave` or `shake`
+    pub glyph_transform: Option<GlyphTransformFn>,
 }
 
 impl Default for TextSettings {
@@ -21,73 +498,417 @@ impl Default for TextSettings {
             color: Color::BLACK,
             alignment: TextAlignment::Left,
             position: Vector2::default(),
+            size: None,
+            line_height: 1.,
+            letter_spacing: 0.,
+            max_width: None,
+            bounds: None,
+            wrap: TextWrap::default(),
+            vertical_alignment: TextVerticalAlignment::Top,
+            overflow: TextOverflow::default(),
+            max_lines: None,
+            space: TextSpace::default(),
+            transform: None,
+            outline: None,
+            glow: None,
+            shadow: None,
+            visible_chars: None,
+            glyph_transform: None,
         }
     }
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub struct LineMetrics {
+    /// Area the line covers from the font's ascent to its descent
+    pub bounds: Rectangle,
+    pub baseline: f32,
+    /// Character indices of the line in the measured text
+    pub chars: Range<usize>,
+}
+
+impl TextSettings {
+    pub(crate) fn layout(&self) -> TextLayout {
+        let line_breaker = match self.wrap {
+            TextWrap::Char => BuiltInLineBreaker::AnyCharLineBreaker,
+            TextWrap::Word | TextWrap::None => BuiltInLineBreaker::UnicodeLineBreaker,
+        };
+
+        TextLayout {
+            layout: Layout::default_wrap()
+                .h_align(self.alignment)
+                .v_align(VerticalAlign::Top)
+                .line_breaker(line_breaker),
+            wrap: self.wrap != TextWrap::None,
+            v_align: self.vertical_alignment,
+            overflow: self.overflow,
+            max_lines: self.max_lines,
+            letter_spacing: self.letter_spacing,
+            line_height: self.line_height,
+        }
+    }
+
+    pub(crate) fn section<'a>(&self, font: &Font, text: &'a str) -> Section<'a> {
+        self.geometry(Section::default().add_text(self.text(font, text)))
+    }
+
+    /// Builds a section of spans, anything a span leaves unset comes from `font` and these settings
+    pub(crate) fn rich_section<'a>(
+        &self,
+        font: &Font,
+        spans: &'a [TextSpan],
+        font_cache: &FontCache,
+    ) -> Section<'a> {
+        let texts = spans
+            .iter()
+            .map(|span| {
+                // each kind of font is drawn differently, so they can't be mixed
+                let span_font = span
+                    .font
+                    .filter(|span_font| {
+                        span_font.kind == font.kind && font_cache.is_alive(span_font)
+                    })
+                    .unwrap_or(*font);
+                let mut text = self.text(&span_font, &span.text);
+                if let Some(size) = span.size {
+                    text = text.with_scale(size);
+                }
+                if let Some(color) = span.color {
+                    text = text.with_color(color.normalize());
+                }
+                text
+            })
+            .collect();
+
+        self.geometry(Section::default().with_text(texts))
+    }
+
+    fn text<'a>(&self, font: &Font, text: &'a str) -> Text<'a> {
+        Text::new(text)
+            .with_font_id(FontId(font.id))
+            .with_scale(self.size.unwrap_or(font.size))
+            .with_color(self.color.normalize())
+    }
+
+    /// Point the text is aligned on, inside of `bounds` when they're set
+    pub(crate) fn anchor(&self) -> Vector2<f32> {
+        let bounds = match self.bounds {
+            Some(bounds) => bounds,
+            None => return self.position,
+        };
+
+        let x = match self.alignment {
+            TextAlignment::Left => bounds.x,
+            TextAlignment::Center => bounds.x + bounds.width / 2.,
+            TextAlignment::Right => bounds.x + bounds.width,
+        };
+        let y = match self.vertical_alignment {
+            TextVerticalAlignment::Top => bounds.y,
+            TextVerticalAlignment::Center => bounds.y + bounds.height / 2.,
+            TextVerticalAlignment::Bottom => bounds.y + bounds.height,
+        };
+
+        Vector2::from([x, y])
+    }
+
+    fn geometry<'a>(&self, section: Section<'a>) -> Section<'a> {
+        let anchor = self.anchor();
+        let size = match self.bounds {
+            Some(bounds) => (bounds.width, bounds.height),
+            None => (self.max_width.unwrap_or(f32::INFINITY), f32::INFINITY),
+        };
+
+        section
+            .with_screen_position((anchor.x, anchor.y))
+            .with_bounds(size)
+    }
+}
+
 impl Renderer {
     pub fn text(&mut self, font: &Font, text: &str, position: Vector2<f32>) {
         self.text_ex(
             font,
             text,
             &TextSettings {
-                color: Color::BLACK,
-                alignment: TextAlignment::Left,
                 position,
+                ..Default::default()
             },
         )
     }
 
     pub fn text_ex(&mut self, font: &Font, text: &str, settings: &TextSettings) {
-        let brush = self.glyph_brushs.get_mut(font).unwrap();
-        let base_text = Text::new(&text);
-        let section = Section::default()
-            .add_text(
-                base_text
-                    .with_scale(40.)
-                    .with_color(settings.color.normalize()),
-            )
-            .with_screen_position((settings.position.x, settings.position.y))
-            .with_layout(
-                Layout::default()
-                    .h_align(settings.alignment)
-                    .v_align(VerticalAlign::Top),
-            );
-        brush.queue(section);
-        self.set_batch_id(&format!("font_{}", font.id));
-    }
-
-    pub(crate) fn render_all_text_queue(&mut self) -> Result<(), SmolError> {
-        // let brush_map: HashMap<Font, GlyphBrush<[f32; 13]>> = .iter().collect();
-        for (font, brush) in &mut self.glyph_brushs {
-            self.context.bind_texture(&font.texture);
-            let brush_action = brush.process_queued(
-                |rect, tex_data| unsafe {
-                    gl::TexSubImage2D(
-                        gl::TEXTURE_2D,
-                        0,
-                        rect.min[0] as _,
-                        rect.min[1] as _,
-                        rect.width() as _,
-                        rect.height() as _,
-                        gl::RED,
-                        gl::UNSIGNED_BYTE,
-                        tex_data.as_ptr() as _,
-                    );
-                },
-                to_vertex,
-            )?;
+        if !self.is_layer_visible() || !self.font_cache.is_alive(font) {
+            return;
+        }
+
+        match font.kind {
+            FontKind::Vector => self.queue_text(self.text_section(font, text, settings), settings),
+            FontKind::Bitmap => {
+                self.bitmap_text(font, &[(text, settings.color.normalize())], settings)
+            }
+            FontKind::Sdf => self.sdf_text(self.text_section(font, text, settings), settings),
+        }
+    }
 
-            match brush_action {
-                BrushAction::Draw(vertices) => self.context.text_pipeline.upload_vertices(vertices),
-                BrushAction::ReDraw => {}
+    /// `settings.section` with characters the font doesn't have moved to its fallbacks
+    pub(crate) fn text_section<'a>(
+        &self,
+        font: &Font,
+        text: &'a str,
+        settings: &TextSettings,
+    ) -> Section<'a> {
+        self.font_cache
+            .apply_fallbacks(settings.section(font, text))
+    }
+
+    /// Laid out glyphs of `text` with byte indices into all of `text`, even when fallbacks split
+    /// it into several runs
+    fn text_glyphs(
+        &mut self,
+        font: &Font,
+        text: &str,
+        settings: &TextSettings,
+    ) -> Vec<SectionGlyph> {
+        let section = self.text_section(font, text, settings);
+        let run_starts: Vec<usize> = section
+            .text
+            .iter()
+            .scan(0, |start, run| {
+                let run_start = *start;
+                *start += run.text.len();
+                Some(run_start)
+            })
+            .collect();
+
+        self.font_cache
+            .brush
+            .glyphs_custom_layout(section, &settings.layout())
+            .map(|glyph| {
+                let mut glyph = glyph.clone();
+                glyph.byte_index += run_starts[glyph.section_index];
+                glyph
+            })
+            .collect()
+    }
+
+    pub(crate) fn queue_text(&mut self, section: Section, settings: &TextSettings) {
+        match settings.space {
+            TextSpace::Screen => self.set_batch_id("text"),
+            TextSpace::World => self.set_batch_id("world_text"),
+        }
+
+        if let Some(transform) = settings.transform {
+            // transformed text can't share a draw with other text, so it's drawn right away
+            let bounds = self.measure_section(section.clone(), settings);
+            let model = text_transform_matrix(bounds, &transform);
+            self.flush_batch();
+            self.queue_section(section, settings);
+            let projection = self.batch_projection() * model;
+            if let Err(error) = self.render_all_text_queue(&projection) {
+                self.text_error = Some(error);
             }
-            self.context
-                .text_pipeline
-                .flush(&self.camera.get_projection_matrix(self.render_size));
+        } else {
+            self.queue_section(section, settings);
         }
+    }
 
-        Ok(())
+    /// Projection of the current batch, screen space text ignores the camera
+    pub(crate) fn batch_projection(&self) -> Matrix4<f32> {
+        match self.current_batch_id.as_deref() {
+            Some(id) if id == "text" || id == "bitmap_text" || id.starts_with("sdf_text") => {
+                self.camera.get_screen_projection_matrix(self.render_size)
+            }
+            _ => self.camera.get_projection_view_matrix(self.render_size),
+        }
+    }
+
+    /// Size and position `text_ex` would draw `text` at
+    pub fn measure_text(&mut self, font: &Font, text: &str, settings: &TextSettings) -> Rectangle {
+        if font.kind == FontKind::Bitmap {
+            return self.measure_bitmap_text(font, &[(text, settings.color.normalize())], settings);
+        }
+
+        self.measure_section(self.text_section(font, text, settings), settings)
+    }
+
+    pub(crate) fn measure_section(
+        &mut self,
+        section: Section,
+        settings: &TextSettings,
+    ) -> Rectangle {
+        let bounds = self
+            .font_cache
+            .brush
+            .glyph_bounds_custom_layout(section, &settings.layout());
+
+        if let Some(bounds) = bounds {
+            Rectangle {
+                x: bounds.min.x,
+                y: bounds.min.y,
+                width: bounds.width(),
+                height: bounds.height(),
+            }
+        } else {
+            let anchor = settings.anchor();
+            Rectangle {
+                x: anchor.x,
+                y: anchor.y,
+                width: 0.,
+                height: 0.,
+            }
+        }
+    }
+
+    pub fn text_lines(
+        &mut self,
+        font: &Font,
+        text: &str,
+        settings: &TextSettings,
+    ) -> Vec<LineMetrics> {
+        if font.kind == FontKind::Bitmap {
+            return self.bitmap_text_lines(font, text, settings);
+        }
+
+        let mut glyphs = self.text_glyphs(font, text, settings);
+        let brush = &mut self.font_cache.brush;
+        let fonts = brush.fonts();
+        let char_indices = char_index_map(text);
+
+        split_lines(&mut glyphs)
+            .into_iter()
+            .map(|line| {
+                let first = &line[0];
+                let last = &line[line.len() - 1];
+                let font = fonts[first.font_id.0].as_scaled(first.glyph.scale);
+                let last_advance = fonts[last.font_id.0]
+                    .as_scaled(last.glyph.scale)
+                    .h_advance(last.glyph.id);
+                let baseline = first.glyph.position.y;
+
+                LineMetrics {
+                    bounds: Rectangle {
+                        x: first.glyph.position.x,
+                        y: baseline - font.ascent(),
+                        width: last.glyph.position.x + last_advance - first.glyph.position.x,
+                        height: font.ascent() - font.descent(),
+                    },
+                    baseline,
+                    chars: char_indices[first.byte_index]..char_indices[last.byte_index] + 1,
+                }
+            })
+            .collect()
+    }
+
+    /// Where a text cursor sits before the character at `char_index`, the width is always 0
+    pub fn caret_position(
+        &mut self,
+        font: &Font,
+        text: &str,
+        settings: &TextSettings,
+        char_index: usize,
+    ) -> Rectangle {
+        if font.kind == FontKind::Bitmap {
+            return self.bitmap_caret_position(font, text, settings, char_index);
+        }
+
+        let scale = settings.size.unwrap_or(font.size);
+        let font_id = font.id;
+        let glyphs = self.text_glyphs(font, text, settings);
+        let brush = &mut self.font_cache.brush;
+        let fonts = brush.fonts();
+        let char_indices = char_index_map(text);
+        let line_advance = {
+            let font = fonts[font_id].as_scaled(scale);
+            (font.height() + font.line_gap()) * settings.line_height
+        };
+
+        let caret = |x: f32, baseline: f32, glyph: Option<&SectionGlyph>| {
+            let font = fonts[glyph.map_or(font_id, |g| g.font_id.0)].as_scaled(scale);
+            Rectangle {
+                x,
+                y: baseline - font.ascent(),
+                width: 0.,
+                height: font.ascent() - font.descent(),
+            }
+        };
+
+        if let Some(glyph) = glyphs
+            .iter()
+            .find(|g| char_indices[g.byte_index] == char_index)
+        {
+            return caret(glyph.glyph.position.x, glyph.glyph.position.y, Some(glyph));
+        }
+
+        let previous = glyphs
+            .iter()
+            .rev()
+            .find(|g| char_indices[g.byte_index] < char_index);
+
+        if let Some(glyph) = previous {
+            let advance = fonts[glyph.font_id.0]
+                .as_scaled(glyph.glyph.scale)
+                .h_advance(glyph.glyph.id);
+            let line_breaks = text
+                .chars()
+                .skip(char_indices[glyph.byte_index])
+                .take(char_index - char_indices[glyph.byte_index])
+                .filter(|c| *c == '\n')
+                .count();
+
+            if line_breaks == 0 {
+                caret(
+                    glyph.glyph.position.x + advance,
+                    glyph.glyph.position.y,
+                    Some(glyph),
+                )
+            } else {
+                caret(
+                    settings.anchor().x,
+                    glyph.glyph.position.y + line_advance * line_breaks as f32,
+                    Some(glyph),
+                )
+            }
+        } else {
+            let ascent = fonts[font_id].as_scaled(scale).ascent();
+            let anchor = settings.anchor();
+            caret(anchor.x, anchor.y + ascent, None)
+        }
+    }
+
+    /// Draws the queued text, when it doesn't fit in the largest glyph cache at once it's drawn
+    /// a piece at a time and the error says so
+    pub(crate) fn render_all_text_queue(
+        &mut self,
+        projection: &Matrix4<f32>,
+    ) -> Result<(), SmolError> {
+        let queued = std::mem::take(&mut self.font_cache.queued);
+        let error = match self.font_cache.process_queued() {
+            Ok(brush_action) => {
+                self.draw_text(brush_action, projection);
+                return Ok(());
+            }
+            Err(error) => error,
+        };
+
+        self.font_cache.clear_queue();
+        for text in &queued {
+            self.font_cache.queue_in_brush(text);
+            match self.font_cache.process_queued() {
+                Ok(brush_action) => self.draw_text(brush_action, projection),
+                Err(_) => self.font_cache.clear_queue(),
+            }
+        }
+
+        Err(error)
+    }
+
+    fn draw_text(&mut self, brush_action: BrushAction<[f32; 13]>, projection: &Matrix4<f32>) {
+        match brush_action {
+            BrushAction::Draw(vertices) => self.context.text_pipeline.upload_vertices(vertices),
+            BrushAction::ReDraw => {}
+        }
+        self.context.bind_texture(&self.font_cache.texture);
+        self.context.text_pipeline.flush(projection);
     }
 }
 
@@ -145,3 +966,44 @@ pub fn to_vertex(
         extra.color[3],
     ]
 }
+
+/// Model matrix of `transform` applied to text covering `bounds`
+pub(crate) fn text_transform_matrix(bounds: Rectangle, transform: &Transform) -> Matrix4<f32> {
+    let anchor = get_anchor_point(
+        transform.anchor,
+        Vector2::from([bounds.width, bounds.height]),
+    );
+    let anchor = Point3::from([bounds.x + anchor.x, bounds.y + anchor.y, 0.]);
+
+    Matrix4::new_translation(&transform.position)
+        * Matrix4::new_rotation_wrt_point(transform.rotation, anchor)
+        * Matrix4::new_nonuniform_scaling_wrt_point(&transform.scale, &anchor)
+}
+
+/// Maps each byte index of `text` to the index of the character it belongs to
+fn char_index_map(text: &str) -> Vec<usize> {
+    let mut map = vec![0; text.len() + 1];
+    for (index, (byte_index, c)) in text.char_indices().enumerate() {
+        map[byte_index..byte_index + c.len_utf8()].fill(index);
+    }
+    map[text.len()] = text.chars().count();
+
+    map
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_font_has_no_glyphs() {
+        let font = empty_font();
+
+        assert_eq!(font.glyph_count(), 1);
+        assert_eq!(font.glyph_id('a').0, 0);
+        assert!(font
+            .outline_glyph(font.glyph_id('a').with_scale(20.))
+            .is_none());
+        assert_eq!(font.as_scaled(20.).h_advance(font.glyph_id('a')), 0.);
+    }
+}
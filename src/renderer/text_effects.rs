@@ -0,0 +1,253 @@
+use glyph_brush::ab_glyph::{Font as _, ScaleFont};
+use glyph_brush::{Extra, GlyphCruncher, GlyphPositioner, Section, SectionGeometry, SectionGlyph};
+use nalgebra::Vector2;
+
+use crate::Color;
+
+use super::shapes::Rectangle;
+use super::text::{QueuedText, TextSettings};
+use super::Renderer;
+
+/// Character of a text handed to `TextSettings::glyph_transform`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextGlyph {
+    /// Index of the character in the text, counting across every span of rich text
+    pub index: usize,
+    pub c: char,
+    /// Laid out position of the character's caret on its baseline
+    pub position: Vector2<f32>,
+}
+
+/// How one character is drawn, relative to where the text laid it out
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GlyphTransform {
+    pub offset: Vector2<f32>,
+    /// Grows the character around its center
+    pub scale: f32,
+    /// Replaces the color of the text
+    pub color: Option<Color>,
+    /// Multiplies the alpha of the character, outline and shadow included
+    pub alpha: f32,
+    pub visible: bool,
+}
+
+/// Callback deciding the transform of each character of a text
+pub type GlyphTransformFn = Box<dyn Fn(&TextGlyph) -> GlyphTransform>;
+
+impl Default for GlyphTransform {
+    fn default() -> Self {
+        GlyphTransform {
+            offset: Vector2::default(),
+            scale: 1.,
+            color: None,
+            alpha: 1.,
+            visible: true,
+        }
+    }
+}
+
+impl GlyphTransform {
+    /// Bobs characters up and down `height` pixels in a wave running along the text
+    pub fn wave(glyph: &TextGlyph, time: f32, height: f32, speed: f32) -> Self {
+        GlyphTransform {
+            offset: Vector2::from([0., (time * speed - glyph.index as f32 * 0.5).sin() * height]),
+            ..Default::default()
+        }
+    }
+
+    /// Jitters characters up to `amount` pixels in random directions, 30 times a second
+    pub fn shake(glyph: &TextGlyph, time: f32, amount: f32) -> Self {
+        let step = (time * 30.) as u32;
+        let index = glyph.index as u32;
+
+        GlyphTransform {
+            offset: Vector2::from([noise(index, step, 0), noise(index, step, 1)]) * amount,
+            ..Default::default()
+        }
+    }
+}
+
+/// Random looking number between -1 and 1 that's the same for the same arguments
+fn noise(index: u32, step: u32, axis: u32) -> f32 {
+    let mut n = index.wrapping_mul(374_761_393)
+        ^ step.wrapping_mul(668_265_263)
+        ^ axis.wrapping_mul(2_246_822_519);
+    n = (n ^ (n >> 13)).wrapping_mul(1_274_126_177);
+    n ^= n >> 16;
+
+    n as f32 / u32::MAX as f32 * 2. - 1.
+}
+
+impl TextSettings {
+    /// How the character is drawn, `None` when the typewriter hasn't reached it yet or it's
+    /// hidden
+    pub(crate) fn transform_glyph(&self, glyph: &TextGlyph) -> Option<GlyphTransform> {
+        if matches!(self.visible_chars, Some(count) if glyph.index >= count) {
+            return None;
+        }
+
+        let transform = self
+            .glyph_transform
+            .as_ref()
+            .map_or_else(GlyphTransform::default, |transform| transform(glyph));
+        Some(transform).filter(|transform| transform.visible)
+    }
+
+    /// Offsets and colors of the copies drawn behind vector and bitmap text for its shadow and
+    /// outline, SDF fonts draw those in their shader instead
+    pub(crate) fn effect_passes(&self) -> Vec<(Vector2<f32>, [f32; 4])> {
+        let mut passes = Vec::new();
+
+        if let Some(shadow) = self.shadow {
+            passes.push((shadow.offset, shadow.color.normalize()));
+        }
+        if let Some(outline) = self.outline {
+            let color = outline.color.normalize();
+            for step in 0..8 {
+                let angle = step as f32 * std::f32::consts::FRAC_PI_4;
+                passes.push((
+                    Vector2::from([angle.cos(), angle.sin()]) * outline.width,
+                    color,
+                ));
+            }
+        }
+
+        passes
+    }
+
+    fn has_effects(&self) -> bool {
+        self.visible_chars.is_some()
+            || self.glyph_transform.is_some()
+            || self.outline.is_some()
+            || self.shadow.is_some()
+    }
+}
+
+/// Color of a character drawn with `transform`
+pub(crate) fn glyph_color(color: [f32; 4], transform: &GlyphTransform) -> [f32; 4] {
+    let mut color = transform.color.map_or(color, |color| color.normalize());
+    color[3] *= transform.alpha;
+    color
+}
+
+/// Moves and grows the quad of a character by its transform
+pub(crate) fn transform_rect(rect: Rectangle, transform: &GlyphTransform) -> Rectangle {
+    let width = rect.width * transform.scale;
+    let height = rect.height * transform.scale;
+
+    Rectangle {
+        x: rect.x - (width - rect.width) / 2. + transform.offset.x,
+        y: rect.y - (height - rect.height) / 2. + transform.offset.y,
+        width,
+        height,
+    }
+}
+
+/// Finds which character of a section's text each laid out glyph is
+pub(crate) struct SectionChars<'a> {
+    texts: Vec<&'a str>,
+    /// Index of the first character of each text
+    starts: Vec<usize>,
+}
+
+impl<'a> SectionChars<'a> {
+    pub fn new(section: &Section<'a>) -> Self {
+        let texts: Vec<&str> = section.text.iter().map(|text| text.text).collect();
+        let starts = texts
+            .iter()
+            .scan(0, |start, text| {
+                let text_start = *start;
+                *start += text.chars().count();
+                Some(text_start)
+            })
+            .collect();
+
+        SectionChars { texts, starts }
+    }
+
+    pub fn glyph(&self, glyph: &SectionGlyph) -> TextGlyph {
+        let text = self.texts[glyph.section_index];
+
+        TextGlyph {
+            index: self.starts[glyph.section_index] + text[..glyph.byte_index].chars().count(),
+            c: text[glyph.byte_index..].chars().next().unwrap_or_default(),
+            position: Vector2::from([glyph.glyph.position.x, glyph.glyph.position.y]),
+        }
+    }
+}
+
+impl Renderer {
+    /// Queues a section of a vector font, text with effects is positioned here instead of by
+    /// the brush so each glyph can be moved and colored on its own
+    pub(crate) fn queue_section(&mut self, section: Section, settings: &TextSettings) {
+        let layout = settings.layout();
+        if !settings.has_effects() {
+            self.font_cache
+                .queue(QueuedText::Section(section.to_owned(), layout));
+            return;
+        }
+
+        let chars = SectionChars::new(&section);
+        let colors: Vec<[f32; 4]> = section.text.iter().map(|text| text.extra.color).collect();
+        let bounds = layout.bounds_rect(&SectionGeometry::from(&section));
+        let brush = &mut self.font_cache.brush;
+        let glyphs: Vec<SectionGlyph> = brush
+            .glyphs_custom_layout(section, &layout)
+            .cloned()
+            .collect();
+        let fonts = brush.fonts();
+
+        let mut placed = Vec::new();
+        for mut glyph in glyphs {
+            let transform = match settings.transform_glyph(&chars.glyph(&glyph)) {
+                Some(transform) => transform,
+                None => continue,
+            };
+
+            let position = &mut glyph.glyph.position;
+            if (transform.scale - 1.).abs() > f32::EPSILON {
+                // grow around the middle of the character instead of its caret
+                let font = fonts[glyph.font_id.0].as_scaled(glyph.glyph.scale);
+                let center_x = position.x + font.h_advance(glyph.glyph.id) / 2.;
+                let center_y = position.y - (font.ascent() + font.descent()) / 2.;
+                position.x = center_x + (position.x - center_x) * transform.scale;
+                position.y = center_y + (position.y - center_y) * transform.scale;
+                glyph.glyph.scale.x *= transform.scale;
+                glyph.glyph.scale.y *= transform.scale;
+            }
+            position.x += transform.offset.x;
+            position.y += transform.offset.y;
+
+            let color = colors[glyph.section_index];
+            placed.push((glyph, color, transform));
+        }
+
+        // pre-positioned glyphs are drawn in order, so the copies behind go first
+        let mut queued = Vec::new();
+        let mut extras = Vec::new();
+        for (offset, color) in settings.effect_passes() {
+            for (glyph, _, transform) in &placed {
+                let mut glyph = glyph.clone();
+                glyph.glyph.position.x += offset.x;
+                glyph.glyph.position.y += offset.y;
+                glyph.section_index = extras.len();
+                queued.push(glyph);
+
+                let mut color = color;
+                color[3] *= transform.alpha;
+                extras.push(Extra { color, z: 0. });
+            }
+        }
+        for (mut glyph, color, transform) in placed {
+            glyph.section_index = extras.len();
+            queued.push(glyph);
+            extras.push(Extra {
+                color: glyph_color(color, &transform),
+                z: 0.,
+            });
+        }
+
+        self.font_cache
+            .queue(QueuedText::Positioned(queued, extras, bounds));
+    }
+}
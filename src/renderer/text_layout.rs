@@ -0,0 +1,288 @@
+use std::hash::{Hash, Hasher};
+
+use glyph_brush::ab_glyph::{point, Font, Rect, ScaleFont};
+use glyph_brush::{
+    BuiltInLineBreaker, GlyphPositioner, HorizontalAlign, Layout, SectionGeometry, SectionGlyph,
+    ToSectionText, VerticalAlign,
+};
+
+/// How text is kept from overflowing its bounds
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum TextOverflow {
+    /// Draws past the bounds
+    #[default]
+    Visible,
+    /// Cuts glyphs off at the edge of the bounds
+    Clip,
+    /// Drops what doesn't fit and ends the last visible line with "…"
+    Ellipsis,
+}
+
+/// Wraps the built in glyph_brush layout with the spacing, alignment and overflow options of
+/// `TextSettings`, the inner layout is always top aligned and gets vertically aligned here
+/// after lines are spaced and truncated
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct TextLayout {
+    pub layout: Layout<BuiltInLineBreaker>,
+    /// Breaks lines wider than the bounds
+    pub wrap: bool,
+    pub v_align: VerticalAlign,
+    pub overflow: TextOverflow,
+    pub max_lines: Option<usize>,
+    /// Extra pixels between each character
+    pub letter_spacing: f32,
+    /// Multiplier of the distance between lines
+    pub line_height: f32,
+}
+
+impl Hash for TextLayout {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.layout.hash(state);
+        self.wrap.hash(state);
+        self.v_align.hash(state);
+        self.overflow.hash(state);
+        self.max_lines.hash(state);
+        self.letter_spacing.to_bits().hash(state);
+        self.line_height.to_bits().hash(state);
+    }
+}
+
+impl TextLayout {
+    fn h_align(&self) -> HorizontalAlign {
+        match self.layout {
+            Layout::SingleLine { h_align, .. } | Layout::Wrap { h_align, .. } => h_align,
+        }
+    }
+
+    /// Spreads characters and lines apart by `letter_spacing` and `line_height`
+    fn apply_spacing(&self, lines: &mut [Vec<SectionGlyph>]) {
+        if self.letter_spacing == 0. && (self.line_height - 1.).abs() < f32::EPSILON {
+            return;
+        }
+
+        let first_line_y = lines.first().map_or(0., |line| line[0].glyph.position.y);
+        let h_align = self.h_align();
+
+        for line in lines.iter_mut() {
+            let spacing = self.letter_spacing * (line.len() as f32 - 1.);
+            let align_offset = match h_align {
+                HorizontalAlign::Left => 0.,
+                HorizontalAlign::Center => -spacing / 2.,
+                HorizontalAlign::Right => -spacing,
+            };
+
+            for (index, glyph) in line.iter_mut().enumerate() {
+                let position = &mut glyph.glyph.position;
+                position.x += align_offset + self.letter_spacing * index as f32;
+                position.y = first_line_y + (position.y - first_line_y) * self.line_height;
+            }
+        }
+    }
+
+    /// Removes trailing characters until the line and an ellipsis end before `max_x`
+    fn ellipsize<F, S>(&self, fonts: &[F], sections: &[S], line: &mut Vec<SectionGlyph>, max_x: f32)
+    where
+        F: Font,
+        S: ToSectionText,
+    {
+        let template = match line.last() {
+            Some(glyph) => glyph.clone(),
+            None => return,
+        };
+        let font = &fonts[template.font_id.0];
+        let scaled = font.as_scaled(template.glyph.scale);
+        let ellipsis = match font.glyph_id('…') {
+            id if id.0 != 0 => vec![id],
+            _ => vec![font.glyph_id('.'); 3],
+        };
+        let ellipsis_width = ellipsis.iter().map(|id| scaled.h_advance(*id)).sum::<f32>()
+            + self.letter_spacing * (ellipsis.len() as f32 - 1.);
+        let (start_x, old_end_x) = line_extent(fonts, line);
+
+        while let Some(last) = line.last() {
+            let end_x = last.glyph.position.x + advance(fonts, last);
+            if end_x + self.letter_spacing + ellipsis_width <= max_x
+                && !is_whitespace(sections, last)
+            {
+                break;
+            }
+            line.pop();
+        }
+
+        let mut caret_x = line.last().map_or(start_x, |last| {
+            last.glyph.position.x + advance(fonts, last) + self.letter_spacing
+        });
+        for id in ellipsis {
+            let mut glyph = template.clone();
+            glyph.glyph.id = id;
+            glyph.glyph.position.x = caret_x;
+            caret_x += scaled.h_advance(id) + self.letter_spacing;
+            line.push(glyph);
+        }
+
+        let (_, end_x) = line_extent(fonts, line);
+        let shift = match self.h_align() {
+            HorizontalAlign::Left => 0.,
+            HorizontalAlign::Center => (old_end_x - end_x) / 2.,
+            HorizontalAlign::Right => old_end_x - end_x,
+        };
+        for glyph in line.iter_mut() {
+            glyph.glyph.position.x += shift;
+        }
+    }
+}
+
+impl GlyphPositioner for TextLayout {
+    fn calculate_glyphs<F, S>(
+        &self,
+        fonts: &[F],
+        geometry: &SectionGeometry,
+        sections: &[S],
+    ) -> Vec<SectionGlyph>
+    where
+        F: Font,
+        S: ToSectionText,
+    {
+        let (bound_w, bound_h) = geometry.bounds;
+        let layout_geometry = SectionGeometry {
+            screen_position: geometry.screen_position,
+            bounds: (
+                if self.wrap { bound_w } else { f32::INFINITY },
+                f32::INFINITY,
+            ),
+        };
+        let mut glyphs = self
+            .layout
+            .calculate_glyphs(fonts, &layout_geometry, sections);
+
+        let mut lines: Vec<Vec<SectionGlyph>> = split_lines(&mut glyphs)
+            .into_iter()
+            .map(|line| line.to_vec())
+            .collect();
+        let line_count = lines.len();
+
+        self.apply_spacing(&mut lines);
+
+        if let Some(max_lines) = self.max_lines {
+            lines.truncate(max_lines);
+        }
+
+        let bounds = self.bounds_rect(geometry);
+        let top = geometry.screen_position.1;
+        match self.overflow {
+            TextOverflow::Visible => {}
+            // the vertical alignment is applied afterwards so lines are measured from the top
+            TextOverflow::Clip => lines.retain(|line| {
+                line[0].glyph.position.y - line_metrics(fonts, line).0 < top + bound_h
+            }),
+            TextOverflow::Ellipsis => lines.retain(|line| {
+                line[0].glyph.position.y - line_metrics(fonts, line).1 <= top + bound_h
+            }),
+        }
+
+        if self.overflow == TextOverflow::Ellipsis {
+            let truncated = lines.len() < line_count;
+            let last_index = lines.len().saturating_sub(1);
+
+            for (index, line) in lines.iter_mut().enumerate() {
+                let overflows = line_extent(fonts, line).1 > bounds.max.x;
+                if overflows || (truncated && index == last_index) {
+                    self.ellipsize(fonts, sections, line, bounds.max.x);
+                }
+            }
+        }
+
+        if let Some(last) = lines.last() {
+            let height = last[0].glyph.position.y - line_metrics(fonts, last).1 - top;
+            let shift = match self.v_align {
+                VerticalAlign::Top => 0.,
+                VerticalAlign::Center => -height / 2.,
+                VerticalAlign::Bottom => -height,
+            };
+
+            for glyph in lines.iter_mut().flatten() {
+                glyph.glyph.position.y += shift;
+            }
+        }
+
+        lines.into_iter().flatten().collect()
+    }
+
+    fn bounds_rect(&self, geometry: &SectionGeometry) -> Rect {
+        if self.overflow == TextOverflow::Visible {
+            return Rect {
+                min: point(f32::NEG_INFINITY, f32::NEG_INFINITY),
+                max: point(f32::INFINITY, f32::INFINITY),
+            };
+        }
+
+        let (x, y) = geometry.screen_position;
+        let (width, height) = geometry.bounds;
+        let (min_x, max_x) = match self.h_align() {
+            HorizontalAlign::Left => (x, x + width),
+            HorizontalAlign::Center => (x - width / 2., x + width / 2.),
+            HorizontalAlign::Right => (x - width, x),
+        };
+        let (min_y, max_y) = match self.v_align {
+            VerticalAlign::Top => (y, y + height),
+            VerticalAlign::Center => (y - height / 2., y + height / 2.),
+            VerticalAlign::Bottom => (y - height, y),
+        };
+
+        Rect {
+            min: point(min_x, min_y),
+            max: point(max_x, max_y),
+        }
+    }
+}
+
+/// Splits laid out glyphs into lines, every glyph on a line shares its baseline
+pub(crate) fn split_lines(glyphs: &mut [SectionGlyph]) -> Vec<&mut [SectionGlyph]> {
+    let mut lines = Vec::new();
+    let mut rest = glyphs;
+
+    while !rest.is_empty() {
+        let baseline = rest[0].glyph.position.y;
+        let len = rest
+            .iter()
+            .position(|g| (g.glyph.position.y - baseline).abs() > f32::EPSILON)
+            .unwrap_or(rest.len());
+        let (line, tail) = rest.split_at_mut(len);
+        lines.push(line);
+        rest = tail;
+    }
+
+    lines
+}
+
+fn advance<F: Font>(fonts: &[F], glyph: &SectionGlyph) -> f32 {
+    fonts[glyph.font_id.0]
+        .as_scaled(glyph.glyph.scale)
+        .h_advance(glyph.glyph.id)
+}
+
+/// Start and end x of a line, from the first glyph's caret to the last one's advance
+fn line_extent<F: Font>(fonts: &[F], line: &[SectionGlyph]) -> (f32, f32) {
+    match (line.first(), line.last()) {
+        (Some(first), Some(last)) => (
+            first.glyph.position.x,
+            last.glyph.position.x + advance(fonts, last),
+        ),
+        _ => (0., 0.),
+    }
+}
+
+/// Largest ascent and descent of the fonts used on a line
+fn line_metrics<F: Font>(fonts: &[F], line: &[SectionGlyph]) -> (f32, f32) {
+    line.iter().fold((0., 0.), |(ascent, descent), glyph| {
+        let font = fonts[glyph.font_id.0].as_scaled(glyph.glyph.scale);
+        (ascent.max(font.ascent()), descent.min(font.descent()))
+    })
+}
+
+fn is_whitespace<S: ToSectionText>(sections: &[S], glyph: &SectionGlyph) -> bool {
+    sections[glyph.section_index].to_section_text().text[glyph.byte_index..]
+        .chars()
+        .next()
+        .is_some_and(char::is_whitespace)
+}
@@ -5,15 +5,36 @@ use nalgebra::Vector;
 use crate::gfx::GfxContext;
 use crate::math::Vector2;
 
+use super::shapes::Rectangle;
+
+pub const DEFAULT_FONT_SIZE: f32 = 40.;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Default)]
+pub(crate) enum FontKind {
+    /// Outline font laid out and rasterized by the shared glyph brush
+    #[default]
+    Vector,
+    /// Font drawn from images, see `App::load_bitmap_font`
+    Bitmap,
+    /// Outline font laid out by the shared glyph brush but drawn from signed distance fields,
+    /// see `App::load_sdf_font`
+    Sdf,
+}
+
 #[derive(Clone, Copy, Default, Debug)]
 pub struct Font {
+    /// Index of the font in the shared glyph brush or the bitmap fonts, depending on `kind`
     pub(crate) id: usize,
-    pub(crate) texture: Texture,
+    pub(crate) kind: FontKind,
+    /// Pixel size used when `TextSettings::size` isn't set
+    pub size: f32,
+    /// Times the brush index was freed before the font took it, see `FontCache::is_alive`
+    pub(crate) generation: u32,
 }
 
 impl PartialEq for Font {
     fn eq(&self, other: &Self) -> bool {
-        self.id == other.id
+        self.id == other.id && self.kind == other.kind && self.generation == other.generation
     }
 }
 
@@ -22,15 +43,25 @@ impl Eq for Font {}
 impl Hash for Font {
     fn hash<H: Hasher>(&self, state: &mut H) {
         self.id.hash(state);
+        self.kind.hash(state);
+        self.generation.hash(state);
     }
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Default)]
 pub struct Texture {
     pub(crate) id: u32,
+    /// Generation of `id` when the texture was made, see `is_alive`
+    generation: u32,
     pub uv_size: Vector2<f32>,
     uv_position: Vector2<f32>,
     texture_size: Vector2<f32>,
+    /// Packed into an atlas turned 90 degrees clockwise, regions of it aren't supported
+    rotated: bool,
+    /// Where the image starts inside of `source_size` when its transparent border was trimmed
+    pub offset: Vector2<f32>,
+    /// Size of the image before it was trimmed, it's drawn as if it still had this size
+    pub source_size: Vector2<f32>,
 }
 
 impl Texture {
@@ -42,16 +73,67 @@ impl Texture {
     ) -> Self {
         Self {
             id,
+            generation: GfxContext::texture_generation(id),
             uv_size,
             uv_position,
             texture_size,
+            rotated: false,
+            offset: Vector2::default(),
+            source_size: uv_size,
+        }
+    }
+
+    /// Texture of an image packed into an atlas, see `AtlasSettings`
+    pub(crate) fn packed(
+        mut self,
+        rotated: bool,
+        offset: Vector2<f32>,
+        source_size: Vector2<f32>,
+    ) -> Self {
+        self.rotated = rotated;
+        self.offset = offset;
+        self.source_size = source_size;
+        self
+    }
+
+    /// Part of this texture starting at `position` from its top left
+    pub(crate) fn region(&self, position: Vector2<f32>, size: Vector2<f32>) -> Self {
+        Self {
+            generation: self.generation,
+            ..Self::new(
+                self.id,
+                size,
+                self.uv_position + position,
+                self.texture_size,
+            )
         }
     }
 
+    /// Whether the texture can still be drawn, copies of an unloaded texture are skipped
+    /// instead of drawing whatever GL put in their id since
+    pub fn is_alive(&self) -> bool {
+        self.generation == GfxContext::texture_generation(self.id)
+    }
+
+    /// Texture coordinates of `part`, measured in fractions of `uv_size`
+    pub(crate) fn get_sub_tex_coords(&self, part: Rectangle) -> [[f32; 2]; 4] {
+        let position = self.uv_size.component_mul(&Vector::from([part.x, part.y]));
+        let size = self
+            .uv_size
+            .component_mul(&Vector::from([part.width, part.height]));
+
+        self.region(position, size).get_tex_coords()
+    }
+
     pub(crate) fn get_tex_coords(&self) -> [[f32; 2]; 4] {
+        let packed_size = if self.rotated {
+            Vector::from([self.uv_size.y, self.uv_size.x])
+        } else {
+            self.uv_size
+        };
         let max = Vector::from([
-            (self.uv_position.x + self.uv_size.x) / self.texture_size.x,
-            (self.uv_position.y + self.uv_size.y) / self.texture_size.y,
+            (self.uv_position.x + packed_size.x) / self.texture_size.x,
+            (self.uv_position.y + packed_size.y) / self.texture_size.y,
         ]);
 
         let min = Vector::from([
@@ -59,6 +141,16 @@ impl Texture {
             self.uv_position.y / self.texture_size.y,
         ]);
 
+        if self.rotated {
+            // the image's top left corner ended up at the top right of the packed image
+            return [
+                [min.x, max.y],
+                [max.x, max.y],
+                [max.x, min.y],
+                [min.x, min.y],
+            ];
+        }
+
         [
             [max.x, max.y], // top-right
             [max.x, min.y], //  bottom_right
@@ -0,0 +1,72 @@
+#version 330 core
+
+out vec4 FragColor;
+
+in vec2 TexCoord;
+in vec4 v_color;
+in float v_texture_index;
+
+uniform sampler2D u_textures[32];
+
+// pixels of text per unit of the distance texture
+uniform float distance_scale;
+uniform vec4 outline_color;
+uniform float outline_width;
+uniform vec4 glow_color;
+uniform float glow_radius;
+uniform vec4 shadow_color;
+uniform vec2 shadow_offset;
+uniform float shadow_softness;
+
+float distance_at(int index, vec2 coords)
+{
+    return (texture(u_textures[index], coords).r - 0.5) * distance_scale;
+}
+
+float coverage(float distance)
+{
+    float width = max(fwidth(distance), 0.0001);
+    return clamp(distance / width + 0.5, 0.0, 1.0);
+}
+
+vec4 over(vec4 top, vec4 bottom)
+{
+    float alpha = top.a + bottom.a * (1.0 - top.a);
+    if (alpha <= 0.0) {
+        return vec4(0.0);
+    }
+    vec3 color = (top.rgb * top.a + bottom.rgb * bottom.a * (1.0 - top.a)) / alpha;
+    return vec4(color, alpha);
+}
+
+void main()
+{
+    int index = int(v_texture_index);
+    float distance = distance_at(index, TexCoord);
+
+    vec4 color = vec4(0.0);
+
+    if (shadow_color.a > 0.0) {
+        float shadow_distance = distance_at(index, TexCoord - shadow_offset);
+        float shadow = shadow_softness > 0.0
+            ? clamp(shadow_distance / shadow_softness + 0.5, 0.0, 1.0)
+            : coverage(shadow_distance);
+        color = over(vec4(shadow_color.rgb, shadow_color.a * shadow), color);
+    }
+
+    if (glow_color.a > 0.0 && glow_radius > 0.0) {
+        float glow = clamp(1.0 + distance / glow_radius, 0.0, 1.0);
+        color = over(vec4(glow_color.rgb, glow_color.a * glow * glow), color);
+    }
+
+    if (outline_color.a > 0.0 && outline_width > 0.0) {
+        color = over(vec4(outline_color.rgb, outline_color.a * coverage(distance + outline_width)), color);
+    }
+
+    color = over(vec4(v_color.rgb, v_color.a * coverage(distance)), color);
+
+    if (color.a <= 0.0) {
+        discard;
+    }
+    FragColor = color;
+}